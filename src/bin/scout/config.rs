@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config_path::get_config_file_path;
+
+/// The settings scout acts on, read from the same file as vega's. Anything
+/// else in the file is left to vega.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ScoutConfig {
+    pub focus_follows_mouse: bool,
+    pub focus_dwell_ms: u64,
+    /// vega commands keyed by chord, or by a sequence of chords separated by
+    /// spaces: `"ctrl+alt+t": "cycle"`, `"ctrl+alt+space s": "stack-cycle"`.
+    /// Binding `mode <name>` enters that mode.
+    pub bindings: HashMap<String, String>,
+    /// Named modes, each with the keys it binds until Escape.
+    pub modes: HashMap<String, HashMap<String, String>>,
+    /// How long a sequence waits for its next key.
+    pub sequence_timeout_ms: u64,
+    /// Keep bound keys from reaching the focused app. macOS only. Needs
+    /// Accessibility access, as scout then filters events rather than only
+    /// watching them.
+    pub grab_hotkeys: bool,
+}
+
+impl Default for ScoutConfig {
+    fn default() -> Self {
+        ScoutConfig {
+            focus_follows_mouse: false,
+            focus_dwell_ms: 150,
+            bindings: HashMap::from([
+                ("ctrl+alt+t".to_string(), "cycle".to_string()),
+                ("ctrl+alt+return".to_string(), "promote".to_string()),
+            ]),
+            modes: HashMap::new(),
+            sequence_timeout_ms: 1000,
+            grab_hotkeys: false,
+        }
+    }
+}
+
+pub fn load_config() -> ScoutConfig {
+    let path = get_config_file_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return ScoutConfig::default();
+    };
+    match serde_json::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            ScoutConfig::default()
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use rdev::{EventType, Key};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::keys::{Chord, ModifierTracker, Modifiers};
+
+/// The mode scout starts in, where the top-level `bindings` apply.
+pub const DEFAULT_MODE: &str = "default";
+
+type Sequence = Vec<Chord>;
+
+/// What to do with an input event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Not part of any binding; the focused app should get it.
+    Pass,
+    /// Part of a binding, such as a step into a sequence or the release of
+    /// a bound key; nothing to run yet.
+    Consume,
+    /// Completes a binding: run this vega command.
+    Run(Vec<String>),
+}
+
+/// What each key sequence does in each mode, and how far along a sequence
+/// the user is. Fed raw `rdev` events, it says which commands to run and
+/// which events belong to bindings.
+///
+/// A binding to `mode <name>` switches modes as well as running the command.
+/// Escape abandons a half-typed sequence, or else leaves any mode that does
+/// not bind it itself.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: HashMap<String, HashMap<Sequence, Vec<String>>>,
+    current: String,
+    modifiers: ModifierTracker,
+    pending: Sequence,
+    last_press: Option<Instant>,
+    timeout: Duration,
+    /// Keys whose press was consumed, so their release is too.
+    swallowed: HashSet<Key>,
+}
+
+/// The mode a `mode <name>` command switches to.
+fn mode_target(command: &[String]) -> Option<&str> {
+    match command {
+        [verb, mode] if verb == "mode" => Some(mode),
+        _ => None,
+    }
+}
+
+fn describe(sequence: &[Chord]) -> String {
+    sequence
+        .iter()
+        .map(Chord::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses one mode's bindings, rejecting any sequence that is bound twice or
+/// that starts a longer one: the longer one could never be typed.
+fn parse_table(table: &HashMap<String, String>) -> Result<HashMap<Sequence, Vec<String>>, String> {
+    let mut parsed: HashMap<Sequence, Vec<String>> = HashMap::new();
+    for (keys, command) in table {
+        let sequence = keys
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Sequence, _>>()?;
+        let command: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        if sequence.is_empty() || command.is_empty() {
+            return Err(format!("Empty binding: '{}'", keys));
+        }
+        if parsed.contains_key(&sequence) {
+            return Err(format!("'{}' is bound twice", describe(&sequence)));
+        }
+        parsed.insert(sequence, command);
+    }
+
+    let mut sequences: Vec<&Sequence> = parsed.keys().collect();
+    sequences.sort_by_key(|s| s.len());
+    for (i, short) in sequences.iter().enumerate() {
+        if let Some(long) = sequences[i + 1..]
+            .iter()
+            .find(|long| long.len() > short.len() && long.starts_with(short))
+        {
+            return Err(format!(
+                "'{}' is bound, so '{}' can never be reached",
+                describe(short),
+                describe(long)
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
+impl Keymap {
+    pub fn new(
+        bindings: &HashMap<String, String>,
+        modes: &HashMap<String, HashMap<String, String>>,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        if modes.contains_key(DEFAULT_MODE) {
+            return Err(format!(
+                "'{}' is the top-level bindings and cannot be declared as a mode",
+                DEFAULT_MODE
+            ));
+        }
+        let mut tables = HashMap::new();
+        tables.insert(DEFAULT_MODE.to_string(), parse_table(bindings)?);
+        for (name, table) in modes {
+            let table = parse_table(table).map_err(|e| format!("In mode {}: {}", name, e))?;
+            tables.insert(name.clone(), table);
+        }
+
+        for command in tables.values().flat_map(HashMap::values) {
+            if command[0] == "mode" {
+                match mode_target(command) {
+                    Some(mode) if tables.contains_key(mode) => {}
+                    Some(mode) => return Err(format!("Unknown mode: {}", mode)),
+                    None => {
+                        return Err(format!(
+                            "Expected 'mode <name>', got '{}'",
+                            command.join(" ")
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(Keymap {
+            bindings: tables,
+            current: DEFAULT_MODE.to_string(),
+            modifiers: ModifierTracker::default(),
+            pending: Vec::new(),
+            last_press: None,
+            timeout,
+            swallowed: HashSet::new(),
+        })
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Corrects the held modifiers from the system's own view of them.
+    pub fn resync_modifiers(&mut self, actual: Modifiers, now: Instant) {
+        self.modifiers.resync(actual, now);
+    }
+
+    /// Handles one input event seen at `now`.
+    pub fn handle(&mut self, event: &EventType, now: Instant) -> Action {
+        // Modifiers always reach the app.
+        if self.modifiers.update(event, now) {
+            return Action::Pass;
+        }
+        match *event {
+            EventType::KeyPress(key) => {
+                self.modifiers.expire(now);
+                let modifiers = self.modifiers.modifiers();
+                let action = self.press(Chord { modifiers, key }, now);
+                if action != Action::Pass {
+                    self.swallowed.insert(key);
+                }
+                action
+            }
+            EventType::KeyRelease(key) => {
+                if self.swallowed.remove(&key) {
+                    Action::Consume
+                } else {
+                    Action::Pass
+                }
+            }
+            _ => Action::Pass,
+        }
+    }
+
+    fn press(&mut self, chord: Chord, now: Instant) -> Action {
+        if self
+            .last_press
+            .is_some_and(|last| now.duration_since(last) > self.timeout)
+        {
+            self.pending.clear();
+        }
+        self.last_press = Some(now);
+
+        let had_pending = !self.pending.is_empty();
+        self.pending.push(chord);
+        match self.advance() {
+            Action::Pass => {}
+            action => return action,
+        }
+
+        if chord == Chord::bare(Key::Escape) {
+            if had_pending {
+                return Action::Consume;
+            }
+            if self.current != DEFAULT_MODE {
+                self.current = DEFAULT_MODE.to_string();
+                return Action::Run(vec!["mode".to_string(), DEFAULT_MODE.to_string()]);
+            }
+            return Action::Pass;
+        }
+        if had_pending {
+            // The sequence went nowhere; this key may still start another.
+            self.pending.push(chord);
+            return self.advance();
+        }
+        Action::Pass
+    }
+
+    /// Runs the pending sequence if it is bound, keeps it if it starts a
+    /// binding, and drops it otherwise.
+    fn advance(&mut self) -> Action {
+        let table = &self.bindings[&self.current];
+        if let Some(command) = table.get(&self.pending).cloned() {
+            self.pending.clear();
+            if let Some(mode) = mode_target(&command) {
+                self.current = mode.to_string();
+            }
+            return Action::Run(command);
+        }
+        if table
+            .keys()
+            .any(|sequence| sequence.starts_with(&self.pending))
+        {
+            return Action::Consume;
+        }
+        self.pending.clear();
+        Action::Pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn table(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn keymap() -> Keymap {
+        let bindings = table(&[
+            ("ctrl+alt+t", "cycle"),
+            ("ctrl+alt+r", "mode resize"),
+            ("ctrl+alt+space w 3", "workspace 3"),
+            ("ctrl+alt+space w 4", "workspace 4"),
+            ("ctrl+alt+space s", "stack-cycle"),
+        ]);
+        let mut modes = HashMap::new();
+        modes.insert(
+            "resize".to_string(),
+            table(&[
+                ("h", "stack left"),
+                ("l", "stack right"),
+                ("return", "mode default"),
+            ]),
+        );
+        Keymap::new(&bindings, &modes, TIMEOUT).unwrap()
+    }
+
+    /// Press and release events for `keys` (chords separated by spaces),
+    /// with the modifiers held around each key.
+    fn events(keys: &str) -> Vec<EventType> {
+        let mut events = Vec::new();
+        for chord in keys.split_whitespace() {
+            let chord: Chord = chord.parse().unwrap();
+            let m = chord.modifiers;
+            let held: Vec<Key> = [
+                (m.ctrl, Key::ControlLeft),
+                (m.alt, Key::Alt),
+                (m.shift, Key::ShiftLeft),
+                (m.meta, Key::MetaLeft),
+            ]
+            .into_iter()
+            .filter_map(|(on, key)| on.then_some(key))
+            .collect();
+
+            events.extend(held.iter().map(|k| EventType::KeyPress(*k)));
+            events.push(EventType::KeyPress(chord.key));
+            events.push(EventType::KeyRelease(chord.key));
+            events.extend(held.iter().map(|k| EventType::KeyRelease(*k)));
+        }
+        events
+    }
+
+    /// Types `keys` at `now`, returning every command produced.
+    fn type_at(keymap: &mut Keymap, keys: &str, now: Instant) -> Vec<String> {
+        events(keys)
+            .iter()
+            .filter_map(|event| match keymap.handle(event, now) {
+                Action::Run(command) => Some(command.join(" ")),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn type_keys(keymap: &mut Keymap, keys: &str) -> Vec<String> {
+        type_at(keymap, keys, Instant::now())
+    }
+
+    #[test]
+    fn chords_run_commands_in_the_default_mode() {
+        let mut keymap = keymap();
+        assert_eq!(type_keys(&mut keymap, "ctrl+alt+t"), ["cycle"]);
+        assert!(type_keys(&mut keymap, "h").is_empty());
+        assert_eq!(keymap.current(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn bare_keys_map_to_commands_inside_a_mode() {
+        let mut keymap = keymap();
+        assert_eq!(type_keys(&mut keymap, "ctrl+alt+r"), ["mode resize"]);
+        assert_eq!(keymap.current(), "resize");
+        assert_eq!(type_keys(&mut keymap, "h l"), ["stack left", "stack right"]);
+        assert_eq!(keymap.current(), "resize");
+    }
+
+    #[test]
+    fn top_level_bindings_are_inactive_inside_a_mode() {
+        let mut keymap = keymap();
+        type_keys(&mut keymap, "ctrl+alt+r");
+        assert!(type_keys(&mut keymap, "ctrl+alt+t j").is_empty());
+        assert_eq!(keymap.current(), "resize");
+    }
+
+    #[test]
+    fn escape_and_mode_bindings_leave_a_mode() {
+        let mut keymap = keymap();
+        type_keys(&mut keymap, "ctrl+alt+r");
+        assert_eq!(type_keys(&mut keymap, "escape"), ["mode default"]);
+        assert_eq!(keymap.current(), DEFAULT_MODE);
+        assert!(type_keys(&mut keymap, "escape").is_empty());
+
+        type_keys(&mut keymap, "ctrl+alt+r");
+        assert_eq!(type_keys(&mut keymap, "return"), ["mode default"]);
+        assert_eq!(keymap.current(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn sequences_run_once_complete() {
+        let mut keymap = keymap();
+        assert!(type_keys(&mut keymap, "ctrl+alt+space w").is_empty());
+        assert_eq!(type_keys(&mut keymap, "3"), ["workspace 3"]);
+        assert_eq!(type_keys(&mut keymap, "ctrl+alt+space s"), ["stack-cycle"]);
+    }
+
+    #[test]
+    fn sequences_time_out_between_keys() {
+        let mut keymap = keymap();
+        let start = Instant::now();
+        type_at(&mut keymap, "ctrl+alt+space w", start);
+        assert!(type_at(&mut keymap, "3", start + TIMEOUT * 2).is_empty());
+        assert_eq!(
+            type_at(&mut keymap, "ctrl+alt+space w 4", start + TIMEOUT * 2),
+            ["workspace 4"]
+        );
+    }
+
+    #[test]
+    fn a_wrong_key_abandons_the_sequence_but_can_start_another() {
+        let mut keymap = keymap();
+        assert!(type_keys(&mut keymap, "ctrl+alt+space x 3").is_empty());
+        assert_eq!(
+            type_keys(&mut keymap, "ctrl+alt+space ctrl+alt+t"),
+            ["cycle"]
+        );
+    }
+
+    #[test]
+    fn escape_cancels_a_sequence_without_leaving_the_mode() {
+        let mut keymap = keymap();
+        assert!(type_keys(&mut keymap, "ctrl+alt+space escape 3").is_empty());
+        assert_eq!(keymap.current(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn only_events_belonging_to_bindings_are_swallowed() {
+        use Action::{Consume, Pass, Run};
+        let mut keymap = keymap();
+        let mut actions = |keys: &str| -> Vec<Action> {
+            events(keys)
+                .iter()
+                .map(|event| keymap.handle(event, Instant::now()))
+                .collect()
+        };
+
+        // Modifiers always reach the app; the bound key's press and release
+        // do not.
+        let cycle = Run(vec!["cycle".to_string()]);
+        assert_eq!(
+            actions("ctrl+alt+t"),
+            [Pass, Pass, cycle, Consume, Pass, Pass]
+        );
+        assert_eq!(
+            actions("ctrl+alt+space"),
+            [Pass, Pass, Consume, Consume, Pass, Pass]
+        );
+        assert_eq!(actions("w"), [Consume, Consume]);
+        // A key that leads nowhere goes through untouched.
+        assert_eq!(actions("x"), [Pass, Pass]);
+    }
+
+    #[test]
+    fn a_lost_release_does_not_leave_plain_keys_bound() {
+        use EventType::{KeyPress, KeyRelease};
+        let mut keymap = keymap();
+        let now = Instant::now();
+        // Ctrl goes down, then secure input swallows its release.
+        for event in [
+            KeyPress(Key::ControlLeft),
+            KeyPress(Key::Alt),
+            KeyPress(Key::KeyL),
+            KeyRelease(Key::KeyL),
+            KeyRelease(Key::Alt),
+        ] {
+            keymap.handle(&event, now);
+        }
+
+        keymap.resync_modifiers(Modifiers::default(), now);
+        assert!(type_at(&mut keymap, "alt+t", now).is_empty());
+    }
+
+    #[test]
+    fn long_held_modifiers_survive_a_resync() {
+        use EventType::{KeyPress, KeyRelease};
+        let mut keymap = keymap();
+        let start = Instant::now();
+        keymap.handle(&KeyPress(Key::ControlLeft), start);
+        keymap.handle(&KeyPress(Key::Alt), start);
+
+        // Both are still down well past the point they would go stale.
+        let later = start + Duration::from_secs(15);
+        let held = Modifiers {
+            ctrl: true,
+            alt: true,
+            ..Modifiers::default()
+        };
+        keymap.resync_modifiers(held, later);
+        assert_eq!(
+            keymap.handle(&KeyPress(Key::KeyT), later),
+            Action::Run(vec!["cycle".to_string()])
+        );
+        keymap.handle(&KeyRelease(Key::KeyT), later);
+    }
+
+    #[test]
+    fn rejects_prefix_conflicts() {
+        let none = HashMap::new();
+        let conflict = table(&[
+            ("ctrl+alt+space", "cycle"),
+            ("ctrl+alt+space w 3", "workspace 3"),
+        ]);
+        let err = Keymap::new(&conflict, &none, TIMEOUT).unwrap_err();
+        assert!(err.contains("ctrl+alt+space w 3"), "{}", err);
+
+        let twice = table(&[("ctrl+t", "cycle"), ("control+T", "promote")]);
+        assert!(Keymap::new(&twice, &none, TIMEOUT).is_err());
+
+        let mut modes = HashMap::new();
+        modes.insert(
+            "launch".to_string(),
+            table(&[("t", "cycle"), ("t t", "promote")]),
+        );
+        assert!(Keymap::new(&HashMap::new(), &modes, TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_config() {
+        let none = HashMap::new();
+        assert!(Keymap::new(&table(&[("ctrl+alt+r", "mode launch")]), &none, TIMEOUT).is_err());
+        assert!(Keymap::new(&table(&[("ctrl+alt+r", "mode")]), &none, TIMEOUT).is_err());
+        assert!(Keymap::new(&table(&[("hyper+r", "cycle")]), &none, TIMEOUT).is_err());
+        assert!(Keymap::new(&table(&[("ctrl+alt+r", " ")]), &none, TIMEOUT).is_err());
+
+        let mut modes = HashMap::new();
+        modes.insert(DEFAULT_MODE.to_string(), HashMap::new());
+        assert!(Keymap::new(&HashMap::new(), &modes, TIMEOUT).is_err());
+    }
+}
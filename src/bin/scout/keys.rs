@@ -0,0 +1,439 @@
+use rdev::{EventType, Key};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How long a modifier may stay down before its release is assumed lost.
+const STALE_AFTER: Duration = Duration::from_secs(10);
+
+#[cfg(not(feature = "x11"))]
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceFlagsState(state_id: i32) -> u64;
+}
+
+/// Which modifiers are held, without distinguishing left from right.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// The modifier `key` stands for, if it is one.
+    pub fn of(key: Key) -> Option<Modifiers> {
+        let mut modifiers = Modifiers::default();
+        match key {
+            Key::ControlLeft | Key::ControlRight => modifiers.ctrl = true,
+            Key::Alt | Key::AltGr => modifiers.alt = true,
+            Key::ShiftLeft | Key::ShiftRight => modifiers.shift = true,
+            Key::MetaLeft | Key::MetaRight => modifiers.meta = true,
+            _ => return None,
+        }
+        Some(modifiers)
+    }
+
+    fn contains(self, other: Modifiers) -> bool {
+        (self.ctrl || !other.ctrl)
+            && (self.alt || !other.alt)
+            && (self.shift || !other.shift)
+            && (self.meta || !other.meta)
+    }
+
+    fn union(self, other: Modifiers) -> Modifiers {
+        Modifiers {
+            ctrl: self.ctrl || other.ctrl,
+            alt: self.alt || other.alt,
+            shift: self.shift || other.shift,
+            meta: self.meta || other.meta,
+        }
+    }
+}
+
+/// The modifiers the system itself considers held right now.
+#[cfg(not(feature = "x11"))]
+pub fn system_modifiers() -> Modifiers {
+    // CGEventSourceStateID
+    const COMBINED_SESSION_STATE: i32 = 0;
+    // CGEventFlags
+    const FLAG_SHIFT: u64 = 1 << 17;
+    const FLAG_CONTROL: u64 = 1 << 18;
+    const FLAG_ALTERNATE: u64 = 1 << 19;
+    const FLAG_COMMAND: u64 = 1 << 20;
+
+    let flags = unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) };
+    Modifiers {
+        ctrl: flags & FLAG_CONTROL != 0,
+        alt: flags & FLAG_ALTERNATE != 0,
+        shift: flags & FLAG_SHIFT != 0,
+        meta: flags & FLAG_COMMAND != 0,
+    }
+}
+
+/// The modifiers the X server considers held right now.
+#[cfg(feature = "x11")]
+pub fn system_modifiers() -> Modifiers {
+    use std::ptr;
+    use std::sync::OnceLock;
+    use x11::xlib;
+
+    // Opened once and kept, as this runs on every key press. Stored as an
+    // address since raw pointers cannot be shared between threads.
+    static DISPLAY: OnceLock<usize> = OnceLock::new();
+    let display = *DISPLAY.get_or_init(|| unsafe { xlib::XOpenDisplay(ptr::null()) as usize })
+        as *mut xlib::Display;
+    if display.is_null() {
+        return Modifiers::default();
+    }
+
+    let mut mask = 0;
+    unsafe {
+        let (mut root, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+        xlib::XQueryPointer(
+            display,
+            xlib::XDefaultRootWindow(display),
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut x,
+            &mut y,
+            &mut mask,
+        );
+    }
+    Modifiers {
+        ctrl: mask & xlib::ControlMask != 0,
+        alt: mask & xlib::Mod1Mask != 0,
+        shift: mask & xlib::ShiftMask != 0,
+        meta: mask & xlib::Mod4Mask != 0,
+    }
+}
+
+/// Modifier keys held down, tracked per physical key so releasing one of
+/// two Ctrl keys leaves Ctrl held. Releases can go missing, for instance
+/// when secure input takes the keyboard or the screen locks, so entries are
+/// also dropped when the system says otherwise or when they grow stale.
+#[derive(Debug, Default)]
+pub struct ModifierTracker {
+    held: HashMap<Key, Instant>,
+}
+
+impl ModifierTracker {
+    /// Records a modifier going down or up. Returns false, changing nothing,
+    /// for any other event.
+    pub fn update(&mut self, event: &EventType, now: Instant) -> bool {
+        match *event {
+            EventType::KeyPress(key) if Modifiers::of(key).is_some() => {
+                self.held.insert(key, now);
+                true
+            }
+            EventType::KeyRelease(key) if Modifiers::of(key).is_some() => {
+                self.held.remove(&key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forgets modifiers that went down more than `STALE_AFTER` ago.
+    pub fn expire(&mut self, now: Instant) {
+        self.held
+            .retain(|_, pressed| now.duration_since(*pressed) <= STALE_AFTER);
+    }
+
+    /// Brings the tracked keys in line with `actual`: drops modifiers it has
+    /// up and adds ones it has down that were pressed while scout was not
+    /// looking. Keys it confirms count as fresh, since a held modifier sends
+    /// no repeats to keep it from going stale.
+    pub fn resync(&mut self, actual: Modifiers, now: Instant) {
+        self.held.retain(|key, pressed| {
+            let held = Modifiers::of(*key).is_some_and(|m| actual.contains(m));
+            if held {
+                *pressed = now;
+            }
+            held
+        });
+        let tracked = self.modifiers();
+        for key in [Key::ControlLeft, Key::Alt, Key::ShiftLeft, Key::MetaLeft] {
+            if let Some(modifier) = Modifiers::of(key)
+                && actual.contains(modifier)
+                && !tracked.contains(modifier)
+            {
+                self.held.insert(key, now);
+            }
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.held
+            .keys()
+            .filter_map(|key| Modifiers::of(*key))
+            .fold(Modifiers::default(), Modifiers::union)
+    }
+}
+
+/// A key pressed while some modifiers are held, written like `ctrl+alt+t`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Chord {
+    pub fn bare(key: Key) -> Self {
+        Chord {
+            modifiers: Modifiers::default(),
+            key,
+        }
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::default();
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" | "option" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "cmd" | "command" | "meta" => modifiers.meta = true,
+                _ => return Err(format!("Unknown modifier '{}' in '{}'", part, s)),
+            }
+        }
+        let key =
+            parse_key(key_name).ok_or_else(|| format!("Unknown key '{}' in '{}'", key_name, s))?;
+        Ok(Chord { modifiers, key })
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.modifiers;
+        for (held, name) in [
+            (m.ctrl, "ctrl"),
+            (m.alt, "alt"),
+            (m.shift, "shift"),
+            (m.meta, "cmd"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("a", Key::KeyA),
+    ("b", Key::KeyB),
+    ("c", Key::KeyC),
+    ("d", Key::KeyD),
+    ("e", Key::KeyE),
+    ("f", Key::KeyF),
+    ("g", Key::KeyG),
+    ("h", Key::KeyH),
+    ("i", Key::KeyI),
+    ("j", Key::KeyJ),
+    ("k", Key::KeyK),
+    ("l", Key::KeyL),
+    ("m", Key::KeyM),
+    ("n", Key::KeyN),
+    ("o", Key::KeyO),
+    ("p", Key::KeyP),
+    ("q", Key::KeyQ),
+    ("r", Key::KeyR),
+    ("s", Key::KeyS),
+    ("t", Key::KeyT),
+    ("u", Key::KeyU),
+    ("v", Key::KeyV),
+    ("w", Key::KeyW),
+    ("x", Key::KeyX),
+    ("y", Key::KeyY),
+    ("z", Key::KeyZ),
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    ("return", Key::Return),
+    ("escape", Key::Escape),
+    ("space", Key::Space),
+    ("tab", Key::Tab),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("left", Key::LeftArrow),
+    ("right", Key::RightArrow),
+    ("up", Key::UpArrow),
+    ("down", Key::DownArrow),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("minus", Key::Minus),
+    ("equal", Key::Equal),
+    ("leftbracket", Key::LeftBracket),
+    ("rightbracket", Key::RightBracket),
+    ("semicolon", Key::SemiColon),
+    ("quote", Key::Quote),
+    ("backquote", Key::BackQuote),
+    ("backslash", Key::BackSlash),
+    ("comma", Key::Comma),
+    ("dot", Key::Dot),
+    ("slash", Key::Slash),
+    ("f1", Key::F1),
+    ("f2", Key::F2),
+    ("f3", Key::F3),
+    ("f4", Key::F4),
+    ("f5", Key::F5),
+    ("f6", Key::F6),
+    ("f7", Key::F7),
+    ("f8", Key::F8),
+    ("f9", Key::F9),
+    ("f10", Key::F10),
+    ("f11", Key::F11),
+    ("f12", Key::F12),
+];
+
+/// Case-insensitive key name, as listed in `KEY_NAMES`. `enter` and `esc`
+/// are accepted too.
+fn parse_key(name: &str) -> Option<Key> {
+    let name = match name.to_lowercase().as_str() {
+        "enter" => "return".to_string(),
+        "esc" => "escape".to_string(),
+        other => other.to_string(),
+    };
+    KEY_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EventType::{KeyPress, KeyRelease};
+
+    fn replay(tracker: &mut ModifierTracker, events: &[EventType], now: Instant) {
+        for event in events {
+            tracker.update(event, now);
+        }
+    }
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        alt: false,
+        shift: false,
+        meta: false,
+    };
+
+    #[test]
+    fn tracks_shift_and_meta_alongside_ctrl_and_alt() {
+        let mut tracker = ModifierTracker::default();
+        let now = Instant::now();
+        replay(
+            &mut tracker,
+            &[
+                KeyPress(Key::ShiftRight),
+                KeyPress(Key::MetaLeft),
+                KeyPress(Key::KeyA),
+            ],
+            now,
+        );
+        assert_eq!(
+            tracker.modifiers(),
+            Modifiers {
+                shift: true,
+                meta: true,
+                ..Modifiers::default()
+            }
+        );
+    }
+
+    #[test]
+    fn releasing_one_side_keeps_the_other_held() {
+        let mut tracker = ModifierTracker::default();
+        replay(
+            &mut tracker,
+            &[
+                KeyPress(Key::ControlLeft),
+                KeyPress(Key::ControlRight),
+                KeyRelease(Key::ControlLeft),
+            ],
+            Instant::now(),
+        );
+        assert_eq!(tracker.modifiers(), CTRL);
+    }
+
+    #[test]
+    fn resync_drops_a_modifier_whose_release_was_lost() {
+        let mut tracker = ModifierTracker::default();
+        let now = Instant::now();
+        // Ctrl+Alt+L locks the screen; Ctrl's release never arrives.
+        replay(
+            &mut tracker,
+            &[
+                KeyPress(Key::ControlLeft),
+                KeyPress(Key::Alt),
+                KeyPress(Key::KeyL),
+                KeyRelease(Key::KeyL),
+                KeyRelease(Key::Alt),
+            ],
+            now,
+        );
+        assert_eq!(tracker.modifiers(), CTRL);
+
+        tracker.resync(Modifiers::default(), now);
+        assert_eq!(tracker.modifiers(), Modifiers::default());
+    }
+
+    #[test]
+    fn resync_adds_modifiers_pressed_unseen() {
+        let mut tracker = ModifierTracker::default();
+        let now = Instant::now();
+        replay(&mut tracker, &[KeyPress(Key::ShiftLeft)], now);
+
+        let actual = Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Modifiers::default()
+        };
+        tracker.resync(actual, now);
+        assert_eq!(tracker.modifiers(), actual);
+        // The real Shift key is still the one tracked, so its release counts.
+        replay(&mut tracker, &[KeyRelease(Key::ShiftLeft)], now);
+        assert_eq!(tracker.modifiers(), CTRL);
+    }
+
+    #[test]
+    fn stale_modifiers_expire() {
+        let mut tracker = ModifierTracker::default();
+        let start = Instant::now();
+        replay(&mut tracker, &[KeyPress(Key::ControlRight)], start);
+        replay(&mut tracker, &[KeyPress(Key::Alt)], start + STALE_AFTER);
+
+        tracker.expire(start + STALE_AFTER + Duration::from_secs(1));
+        assert_eq!(
+            tracker.modifiers(),
+            Modifiers {
+                alt: true,
+                ..Modifiers::default()
+            }
+        );
+    }
+}
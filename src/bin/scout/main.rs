@@ -0,0 +1,168 @@
+mod config;
+#[path = "../../config_path.rs"]
+mod config_path;
+mod keymap;
+mod keys;
+
+use rdev::{Event, EventType, listen};
+#[cfg(target_os = "macos")]
+use std::cell::RefCell;
+use std::env;
+use std::process::Command;
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use keymap::{Action, Keymap};
+use keys::system_modifiers;
+
+fn find_vega_executable() -> String {
+    let mut path = env::current_exe().expect("Failed to find current exe path");
+    path.pop();
+    path.push("vega");
+    path.to_string_lossy().into_owned()
+}
+
+/// Runs `vega focus-at` once the pointer has rested for `dwell`, so sweeping
+/// across windows on the way elsewhere does not drag focus along.
+fn spawn_focus_follower(vega_path: String, dwell: Duration) -> Sender<(f64, f64)> {
+    let (tx, rx) = channel::<(f64, f64)>();
+    thread::spawn(move || {
+        while let Ok(mut point) = rx.recv() {
+            loop {
+                match rx.recv_timeout(dwell) {
+                    Ok(next) => point = next,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            let (x, y) = point;
+            Command::new(&vega_path)
+                .args(["focus-at", &x.to_string(), &y.to_string()])
+                .status()
+                .ok();
+        }
+    });
+    tx
+}
+
+/// Runs vega commands one at a time, in the order their keys were pressed,
+/// without holding up the event callback.
+fn spawn_command_runner(vega_path: String) -> Sender<Vec<String>> {
+    let (tx, rx) = channel::<Vec<String>>();
+    thread::spawn(move || {
+        for command in rx {
+            Command::new(&vega_path).args(&command).status().ok();
+        }
+    });
+    tx
+}
+
+struct Scout {
+    keymap: Keymap,
+    runner: Sender<Vec<String>>,
+    focus_follower: Option<Sender<(f64, f64)>>,
+    buttons_held: usize,
+}
+
+impl Scout {
+    /// Acts on one input event. Returns whether it belongs to a binding and
+    /// should be kept from the focused app.
+    fn handle(&mut self, event: &Event) -> bool {
+        match event.event_type {
+            EventType::KeyPress(_) | EventType::KeyRelease(_) => {
+                let now = Instant::now();
+                if let EventType::KeyPress(_) = event.event_type {
+                    self.keymap.resync_modifiers(system_modifiers(), now);
+                }
+                match self.keymap.handle(&event.event_type, now) {
+                    Action::Pass => false,
+                    Action::Consume => true,
+                    Action::Run(command) => {
+                        if command[0] == "mode" {
+                            println!("Mode: {}", self.keymap.current());
+                        }
+                        self.runner.send(command).ok();
+                        true
+                    }
+                }
+            }
+            EventType::ButtonPress(_) => {
+                self.buttons_held += 1;
+                false
+            }
+            EventType::ButtonRelease(_) => {
+                self.buttons_held = self.buttons_held.saturating_sub(1);
+                false
+            }
+            // Focus stays put while dragging, e.g. when moving a window by hand.
+            EventType::MouseMove { x, y } if self.buttons_held == 0 => {
+                if let Some(follower) = &self.focus_follower {
+                    follower.send((x, y)).ok();
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+fn main() {
+    println!("Listening...");
+    let vega_path = find_vega_executable();
+    println!("Main program located at: {}", vega_path);
+
+    let config = config::load_config();
+    let focus_follower = config.focus_follows_mouse.then(|| {
+        spawn_focus_follower(
+            vega_path.clone(),
+            Duration::from_millis(config.focus_dwell_ms),
+        )
+    });
+
+    let keymap = match Keymap::new(
+        &config.bindings,
+        &config.modes,
+        Duration::from_millis(config.sequence_timeout_ms),
+    ) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            eprintln!("Invalid keybindings: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut scout = Scout {
+        keymap,
+        runner: spawn_command_runner(vega_path),
+        focus_follower,
+        buttons_held: 0,
+    };
+
+    #[cfg(target_os = "macos")]
+    if config.grab_hotkeys {
+        // The tap callback must return quickly or macOS disables the tap, so
+        // everything slow happens on the runner threads.
+        let scout = RefCell::new(scout);
+        if let Err(error) = rdev::grab(move |event: Event| {
+            if scout.borrow_mut().handle(&event) {
+                None
+            } else {
+                Some(event)
+            }
+        }) {
+            println!("Error: {:?}", error)
+        }
+        return;
+    }
+    #[cfg(not(target_os = "macos"))]
+    if config.grab_hotkeys {
+        eprintln!("Ignoring grab_hotkeys: grabbing input is only supported on macOS");
+    }
+
+    if let Err(error) = listen(move |event: Event| {
+        scout.handle(&event);
+    }) {
+        println!("Error: {:?}", error)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::geometry::Rect;
+
+/// Where the focus border overlay goes for one window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BorderGeometry {
+    /// Frame of the overlay, in the same top-left coordinates as `window`.
+    pub frame: Rect,
+    /// Corner radius of the overlay's outer edge.
+    pub radius: f64,
+}
+
+/// Places a `width` stroke around `window`. The stroke is pushed out into
+/// the gap as far as it can go without reaching the next tile (half the
+/// gap); whatever does not fit overlaps the window's own edge. The radius
+/// grows with the outset so the inner curve still follows the window.
+pub fn border_geometry(window: Rect, width: f64, radius: f64, gap: f64) -> BorderGeometry {
+    let outset = width.min(gap / 2.0).max(0.0);
+    BorderGeometry {
+        frame: window.inset(-outset),
+        radius: if radius > 0.0 { radius + outset } else { 0.0 },
+    }
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA` into RGBA components from 0.0 to 1.0.
+pub fn parse_color(color: &str) -> Option<[f64; 4]> {
+    let hex = color.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut rgba = [1.0; 4];
+    for (i, component) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        let byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        *component = byte as f64 / 255.0;
+    }
+    Some(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Rect = Rect {
+        x: 100.0,
+        y: 50.0,
+        width: 800.0,
+        height: 600.0,
+    };
+
+    #[test]
+    fn without_gaps_the_border_covers_the_window_edge() {
+        let border = border_geometry(WINDOW, 4.0, 10.0, 0.0);
+        assert_eq!(border.frame, WINDOW);
+        assert_eq!(border.radius, 10.0);
+    }
+
+    #[test]
+    fn wide_gaps_hold_the_whole_stroke() {
+        let border = border_geometry(WINDOW, 4.0, 10.0, 20.0);
+        assert_eq!(
+            border.frame,
+            Rect {
+                x: 96.0,
+                y: 46.0,
+                width: 808.0,
+                height: 608.0,
+            }
+        );
+        assert_eq!(border.radius, 14.0);
+    }
+
+    #[test]
+    fn narrow_gaps_stop_at_the_neighbouring_tile() {
+        let border = border_geometry(WINDOW, 6.0, 0.0, 4.0);
+        assert_eq!(
+            border.frame,
+            Rect {
+                x: 98.0,
+                y: 48.0,
+                width: 804.0,
+                height: 604.0,
+            }
+        );
+        assert_eq!(border.radius, 0.0);
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#FF0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(
+            parse_color("#00ff0080"),
+            Some([0.0, 1.0, 0.0, 128.0 / 255.0])
+        );
+        assert_eq!(parse_color("FF0000"), None);
+        assert_eq!(parse_color("#FF00"), None);
+        assert_eq!(parse_color("#GG0000"), None);
+    }
+}
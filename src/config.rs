@@ -0,0 +1,220 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+pub use crate::config_path::get_config_file_path;
+use crate::core_graphics::Display;
+use crate::geometry::Insets;
+use crate::layout::Layout;
+use crate::order::Insertion;
+
+/// User settings from `~/.config/vega/config.json`. Every field is optional.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Shell commands keyed by event name, e.g. `"layout_changed": "..."`.
+    pub hooks: HashMap<String, String>,
+    /// How long a hook may run before it is killed. 0 lets hooks run for as
+    /// long as they like.
+    pub hook_timeout_ms: u64,
+    /// Layouts `vega cycle` steps through, in order.
+    pub cycle: Vec<Layout>,
+    /// Windows toggled with `vega scratchpad toggle <name>`.
+    pub scratchpads: HashMap<String, ScratchpadConfig>,
+    /// Number of arrangements `vega undo` can step back through.
+    pub history_limit: usize,
+    /// Space between tiles and around the edge of the display, in points.
+    /// Negative values are ignored.
+    pub gap: f64,
+    /// Space to keep clear along each edge of the main display, e.g. for a
+    /// status bar such as SketchyBar. Measured from the display edge, so it
+    /// only matters where it exceeds the menu bar, Dock or notch.
+    pub reserve: Insets,
+    /// Per-display settings keyed by the display's index in `vega query
+    /// displays`, its name or its UUID.
+    pub displays: HashMap<String, DisplayConfig>,
+    /// Overlay drawn by `vega daemon` around the focused tiled window.
+    pub border: BorderConfig,
+    /// Have scout focus the tiled window under the pointer once it rests.
+    pub focus_follows_mouse: bool,
+    /// How long the pointer must rest before focus follows it.
+    pub focus_dwell_ms: u64,
+    /// Move the pointer to the centre of windows that vega commands focus.
+    pub mouse_follows_focus: bool,
+    /// Windows vega leaves alone: never tiled, stacked or focused.
+    pub ignore: Vec<WindowRule>,
+    /// Where windows join the tiling order when they first appear. The
+    /// first matching rule applies.
+    pub order: Vec<OrderRule>,
+    /// Where new windows join the tiling order among windows of the same
+    /// priority.
+    pub new_windows: Insertion,
+}
+
+/// Matches windows by app name (exact) and/or title (substring). An empty
+/// rule matches nothing.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct WindowRule {
+    pub app: Option<String>,
+    pub title: Option<String>,
+}
+
+impl WindowRule {
+    pub fn matches(&self, app: &str, title: &str) -> bool {
+        if self.app.is_none() && self.title.is_none() {
+            return false;
+        }
+        self.app.as_deref().is_none_or(|a| a == app)
+            && self.title.as_deref().is_none_or(|t| title.contains(t))
+    }
+}
+
+/// Places matching windows in the tiling order, e.g. an editor as master or
+/// a chat app at the bottom of the stack. Only new windows are placed, so
+/// `vega promote` and other manual moves stick.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct OrderRule {
+    #[serde(flatten)]
+    pub rule: WindowRule,
+    /// Windows go after those with the same or higher priority and ahead of
+    /// those with lower. Unmatched windows have priority 0.
+    pub priority: i32,
+    /// A fixed position instead: 0 is master, -1 the bottom of the stack.
+    /// The window stays there as others join, until it is moved by hand.
+    pub slot: Option<isize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScratchpadConfig {
+    #[serde(flatten)]
+    pub rule: WindowRule,
+    pub width: f64,
+    pub height: f64,
+    /// Start `app` with `open -a` when no window matches.
+    pub launch: bool,
+}
+
+impl Default for ScratchpadConfig {
+    fn default() -> Self {
+        ScratchpadConfig {
+            rule: WindowRule::default(),
+            width: 1000.0,
+            height: 600.0,
+            launch: false,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            hooks: HashMap::new(),
+            hook_timeout_ms: 5000,
+            cycle: Layout::ALL.to_vec(),
+            scratchpads: HashMap::new(),
+            history_limit: 50,
+            gap: 0.0,
+            reserve: Insets::default(),
+            displays: HashMap::new(),
+            border: BorderConfig::default(),
+            focus_follows_mouse: false,
+            focus_dwell_ms: 150,
+            mouse_follows_focus: false,
+            ignore: Vec::new(),
+            order: Vec::new(),
+            new_windows: Insertion::End,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Replaces the top-level `reserve` on this display.
+    pub reserve: Option<Insets>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BorderConfig {
+    pub enabled: bool,
+    /// `#RRGGBB` or `#RRGGBBAA`.
+    pub color: String,
+    pub width: f64,
+    pub radius: f64,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        BorderConfig {
+            enabled: false,
+            color: "#5E81AC".to_string(),
+            width: 4.0,
+            radius: 10.0,
+        }
+    }
+}
+
+impl Config {
+    /// `self` with values that would put windows off screen replaced by
+    /// their defaults.
+    fn checked(mut self) -> Config {
+        if self.gap < 0.0 {
+            eprintln!("Ignoring gap {}: it must not be negative", self.gap);
+            self.gap = Config::default().gap;
+        }
+        self
+    }
+
+    pub fn ignores(&self, app: &str, title: &str) -> bool {
+        self.ignore.iter().any(|rule| rule.matches(app, title))
+    }
+
+    /// The configured reserve for `display`. An entry keyed by UUID wins
+    /// over one keyed by name, which wins over one keyed by index.
+    pub fn reserve_for(&self, display: &Display) -> Insets {
+        let uuid_key = self
+            .displays
+            .iter()
+            .find(|(key, _)| !display.uuid.is_empty() && key.eq_ignore_ascii_case(&display.uuid))
+            .map(|(_, entry)| entry);
+        uuid_key
+            .or_else(|| {
+                (!display.name.is_empty())
+                    .then(|| self.displays.get(&display.name))
+                    .flatten()
+            })
+            .or_else(|| self.displays.get(&display.index.to_string()))
+            .and_then(|entry| entry.reserve)
+            .unwrap_or(self.reserve)
+    }
+}
+
+pub fn load_config() -> Config {
+    let path = get_config_file_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Config::default();
+    };
+    match serde_json::from_str::<Config>(&content) {
+        Ok(config) => config.checked(),
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            Config::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_gaps_are_ignored() {
+        let config: Config = serde_json::from_str(r#"{ "gap": -100 }"#).unwrap();
+        assert_eq!(config.checked().gap, 0.0);
+        let config: Config = serde_json::from_str(r#"{ "gap": 12 }"#).unwrap();
+        assert_eq!(config.checked().gap, 12.0);
+    }
+}
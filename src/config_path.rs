@@ -0,0 +1,18 @@
+use std::env;
+use std::path::PathBuf;
+
+/// The config file vega and scout share. scout includes this file by path,
+/// so it must not depend on the rest of vega.
+pub fn get_config_file_path() -> PathBuf {
+    let mut path = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut home = PathBuf::from(env::var_os("HOME").unwrap_or_default());
+            home.push(".config");
+            home
+        }
+    };
+    path.push("vega");
+    path.push("config.json");
+    path
+}
@@ -1,4 +1,13 @@
-use crate::geometry::Rect;
+use objc2::encode::{Encode, Encoding, RefEncode};
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send, sel};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+use crate::geometry::{Insets, Rect};
+use crate::window::CFRelease;
 
 #[link(name = "CoreGraphics", kind = "framework")]
 unsafe extern "C" {}
@@ -26,6 +35,72 @@ pub struct CGRect {
     pub size: CGSize,
 }
 
+unsafe impl Encode for CGPoint {
+    const ENCODING: Encoding = Encoding::Struct("CGPoint", &[f64::ENCODING, f64::ENCODING]);
+}
+
+unsafe impl Encode for CGSize {
+    const ENCODING: Encoding = Encoding::Struct("CGSize", &[f64::ENCODING, f64::ENCODING]);
+}
+
+unsafe impl Encode for CGRect {
+    const ENCODING: Encoding = Encoding::Struct("CGRect", &[CGPoint::ENCODING, CGSize::ENCODING]);
+}
+
+/// AppKit's `NSEdgeInsets`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct NSEdgeInsets {
+    pub top: f64,
+    pub left: f64,
+    pub bottom: f64,
+    pub right: f64,
+}
+
+unsafe impl Encode for NSEdgeInsets {
+    const ENCODING: Encoding = Encoding::Struct(
+        "NSEdgeInsets",
+        &[f64::ENCODING, f64::ENCODING, f64::ENCODING, f64::ENCODING],
+    );
+}
+
+impl From<Rect> for CGRect {
+    fn from(rect: Rect) -> Self {
+        CGRect {
+            origin: CGPoint {
+                x: rect.x,
+                y: rect.y,
+            },
+            size: CGSize {
+                width: rect.width,
+                height: rect.height,
+            },
+        }
+    }
+}
+
+/// Opaque `CGColor`; only ever handled through `CGColorRef` pointers.
+#[repr(C)]
+pub struct CGColor {
+    _private: [u8; 0],
+}
+
+unsafe impl RefEncode for CGColor {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Encoding::Struct("CGColor", &[]));
+}
+
+pub type CGColorRef = *const CGColor;
+
+pub type CGDisplayModeRef = *const c_void;
+
+type CFTypeRef = *const c_void;
+
+type CGDisplayReconfigurationCallBack =
+    extern "C" fn(display: CGDirectDisplayID, flags: u32, user_info: *mut c_void);
+
+// kCGDisplayBeginConfigurationFlag
+const DISPLAY_BEGIN_CONFIGURATION: u32 = 1;
+
 unsafe extern "C" {
     pub fn CGMainDisplayID() -> u32;
     pub fn CGDisplayBounds(display: u32) -> CGRect;
@@ -34,6 +109,20 @@ unsafe extern "C" {
         active_displays: *mut CGDirectDisplayID,
         display_count: *mut u32,
     ) -> i32;
+    pub fn CGColorCreateSRGB(red: f64, green: f64, blue: f64, alpha: f64) -> CGColorRef;
+    pub fn CGColorRelease(color: CGColorRef);
+    pub fn CGWarpMouseCursorPosition(new_position: CGPoint) -> i32;
+    pub fn CGAssociateMouseAndMouseCursorPosition(connected: u32) -> i32;
+    pub fn CGDisplayCopyDisplayMode(display: u32) -> CGDisplayModeRef;
+    pub fn CGDisplayModeGetWidth(mode: CGDisplayModeRef) -> usize;
+    pub fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+    pub fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: CGDisplayReconfigurationCallBack,
+        user_info: *mut c_void,
+    ) -> i32;
+    fn CGDisplayCreateUUIDFromDisplayID(display: CGDirectDisplayID) -> CFTypeRef;
+    fn CFUUIDCreateString(allocator: CFTypeRef, uuid: CFTypeRef) -> CFTypeRef;
 }
 
 pub fn main_screen_rect() -> Rect {
@@ -49,26 +138,249 @@ pub fn main_screen_rect() -> Rect {
     }
 }
 
-pub fn all_display_rects() -> Vec<Rect> {
+/// Space the menu bar, Dock and camera housing take from each edge of the
+/// main display, going by AppKit's visible frame and safe area.
+pub fn main_screen_insets() -> Insets {
+    let Some(nsscreen) = AnyClass::get(c"NSScreen") else {
+        return Insets::default();
+    };
+    unsafe {
+        let screens: *mut AnyObject = msg_send![nsscreen, screens];
+        let count: usize = msg_send![screens, count];
+        if count == 0 {
+            return Insets::default();
+        }
+        // The first screen holds the menu bar, making it the main display.
+        let screen: *mut AnyObject = msg_send![screens, objectAtIndex: 0usize];
+        let frame: CGRect = msg_send![screen, frame];
+        let visible: CGRect = msg_send![screen, visibleFrame];
+
+        // AppKit measures from the bottom left, so the menu bar is what the
+        // visible frame leaves above its top edge.
+        let mut insets = Insets {
+            top: (frame.origin.y + frame.size.height) - (visible.origin.y + visible.size.height),
+            bottom: visible.origin.y - frame.origin.y,
+            left: visible.origin.x - frame.origin.x,
+            right: (frame.origin.x + frame.size.width) - (visible.origin.x + visible.size.width),
+        };
+
+        // With the menu bar hidden the visible frame reaches the top edge,
+        // notch or not. The safe area still keeps clear of the notch.
+        let has_safe_area: bool = msg_send![screen, respondsToSelector: sel!(safeAreaInsets)];
+        if has_safe_area {
+            let safe_area: NSEdgeInsets = msg_send![screen, safeAreaInsets];
+            insets.top = insets.top.max(safe_area.top);
+        }
+        insets
+    }
+}
+
+/// Backing pixels per point on the main display: 2 on Retina panels, 1
+/// otherwise.
+pub fn main_screen_scale() -> f64 {
+    display_scale(unsafe { CGMainDisplayID() })
+}
+
+fn display_scale(display_id: CGDirectDisplayID) -> f64 {
+    unsafe {
+        let mode = CGDisplayCopyDisplayMode(display_id);
+        if mode.is_null() {
+            return 1.0;
+        }
+        let scale = CGDisplayModeGetPixelWidth(mode) as f64 / CGDisplayModeGetWidth(mode) as f64;
+        CGDisplayModeRelease(mode);
+        if scale.is_finite() && scale >= 1.0 {
+            scale
+        } else {
+            1.0
+        }
+    }
+}
+
+extern "C" fn display_reconfigured(
+    _display: CGDirectDisplayID,
+    flags: u32,
+    user_info: *mut c_void,
+) {
+    let callback = unsafe { &mut *(user_info as *mut Box<dyn FnMut(bool)>) };
+    callback(flags & DISPLAY_BEGIN_CONFIGURATION != 0);
+}
+
+/// Calls `callback` on the main run loop whenever displays are added,
+/// removed or rearranged: with `true` before the change, while windows are
+/// still in place, and with `false` once it is done. Each display taking
+/// part reports separately, so both can come several times.
+pub fn on_display_reconfigured(callback: impl FnMut(bool) + 'static) {
+    let callback: Box<Box<dyn FnMut(bool)>> = Box::new(Box::new(callback));
+    // Leaked on purpose: the callback stays registered for the process.
+    let user_info = Box::into_raw(callback) as *mut c_void;
+    unsafe {
+        CGDisplayRegisterReconfigurationCallback(display_reconfigured, user_info);
+    }
+}
+
+/// Moves the pointer without posting a mouse event.
+pub fn warp_mouse(x: f64, y: f64) {
+    unsafe {
+        CGWarpMouseCursorPosition(CGPoint { x, y });
+        // Warping detaches the pointer from the mouse for a moment; reattach
+        // it so the next movement is not swallowed.
+        CGAssociateMouseAndMouseCursorPosition(1);
+    }
+}
+
+/// Active displays in the order CoreGraphics lists them.
+fn active_display_ids() -> Vec<CGDirectDisplayID> {
     const MAX_DISPLAYS: usize = 16;
     let mut displays = [0u32; MAX_DISPLAYS];
     let mut count = 0u32;
-    let mut rects = Vec::new();
 
     unsafe {
-        if CGGetActiveDisplayList(MAX_DISPLAYS as u32, displays.as_mut_ptr(), &mut count) == 0 {
-            for &display_id in &displays[..count as usize] {
-                let bounds = CGDisplayBounds(display_id);
+        if CGGetActiveDisplayList(MAX_DISPLAYS as u32, displays.as_mut_ptr(), &mut count) != 0 {
+            return Vec::new();
+        }
+    }
+    displays[..count as usize].to_vec()
+}
+
+pub type DisplayId = CGDirectDisplayID;
+
+/// An active display and the ways config and the command line can name it.
+#[derive(Debug, Clone)]
+pub struct Display {
+    pub id: DisplayId,
+    /// Position in [`displays`].
+    pub index: usize,
+    /// Stays the same across reboots and reconnections, unlike `id` or the
+    /// display's place in [`displays`]. Per-display state is keyed by it.
+    pub uuid: String,
+    /// As System Settings shows it, e.g. "Built-in Retina Display".
+    pub name: String,
+    /// Backing pixels per point.
+    pub scale: f64,
+    /// Holds the menu bar; tiling happens here.
+    pub main: bool,
+    /// In global coordinates, top-left origin.
+    pub bounds: Rect,
+}
+
+impl Display {
+    /// A display at 1x, the main one when `index` is 0.
+    #[cfg(test)]
+    pub fn new(index: usize, uuid: &str, name: &str, bounds: Rect) -> Display {
+        Display {
+            id: Default::default(),
+            index,
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            scale: 1.0,
+            main: index == 0,
+            bounds,
+        }
+    }
+
+    /// Whether `selector` is this display's index, name or UUID.
+    pub fn matches(&self, selector: &str) -> bool {
+        selector == self.index.to_string()
+            || (!self.name.is_empty() && selector == self.name)
+            || (!self.uuid.is_empty() && selector.eq_ignore_ascii_case(&self.uuid))
+    }
+}
 
-                rects.push(Rect {
+fn display_uuid(display_id: CGDirectDisplayID) -> String {
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display_id);
+        if uuid.is_null() {
+            return String::new();
+        }
+        let string = CFUUIDCreateString(ptr::null(), uuid);
+        CFRelease(uuid);
+        if string.is_null() {
+            return String::new();
+        }
+        let text = ns_string(string as *mut AnyObject);
+        CFRelease(string);
+        text
+    }
+}
+
+fn ns_string(string: *mut AnyObject) -> String {
+    if string.is_null() {
+        return String::new();
+    }
+    unsafe {
+        let c_str: *const c_char = msg_send![string, UTF8String];
+        if c_str.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(c_str).to_string_lossy().into_owned()
+    }
+}
+
+/// Display names keyed by display ID, from AppKit's screens.
+fn screen_names() -> HashMap<CGDirectDisplayID, String> {
+    let mut names = HashMap::new();
+    let (Some(nsscreen), Some(nsstring)) = (AnyClass::get(c"NSScreen"), AnyClass::get(c"NSString"))
+    else {
+        return names;
+    };
+    unsafe {
+        let number_key: *mut AnyObject =
+            msg_send![nsstring, stringWithUTF8String: c"NSScreenNumber".as_ptr()];
+        let screens: *mut AnyObject = msg_send![nsscreen, screens];
+        let count: usize = msg_send![screens, count];
+        for i in 0..count {
+            let screen: *mut AnyObject = msg_send![screens, objectAtIndex: i];
+            let has_name: bool = msg_send![screen, respondsToSelector: sel!(localizedName)];
+            if !has_name {
+                continue;
+            }
+            let description: *mut AnyObject = msg_send![screen, deviceDescription];
+            let number: *mut AnyObject = msg_send![description, objectForKey: number_key];
+            if number.is_null() {
+                continue;
+            }
+            let display_id: u32 = msg_send![number, unsignedIntValue];
+            let name: *mut AnyObject = msg_send![screen, localizedName];
+            names.insert(display_id, ns_string(name));
+        }
+    }
+    names
+}
+
+/// Active displays, main first and the rest from left to right, so indices
+/// stay put however CoreGraphics happens to list them.
+pub fn displays() -> Vec<Display> {
+    let names = screen_names();
+    let main = unsafe { CGMainDisplayID() };
+    let mut displays: Vec<Display> = active_display_ids()
+        .into_iter()
+        .map(|id| {
+            let bounds = unsafe { CGDisplayBounds(id) };
+            Display {
+                id,
+                index: 0,
+                uuid: display_uuid(id),
+                name: names.get(&id).cloned().unwrap_or_default(),
+                scale: display_scale(id),
+                main: id == main,
+                bounds: Rect {
                     x: bounds.origin.x,
                     y: bounds.origin.y,
                     width: bounds.size.width,
                     height: bounds.size.height,
-                });
+                },
             }
-        }
+        })
+        .collect();
+    displays.sort_by(|a, b| {
+        b.main
+            .cmp(&a.main)
+            .then(a.bounds.x.total_cmp(&b.bounds.x))
+            .then(a.bounds.y.total_cmp(&b.bounds.y))
+    });
+    for (index, display) in displays.iter_mut().enumerate() {
+        display.index = index;
     }
-
-    rects
+    displays
 }
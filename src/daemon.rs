@@ -0,0 +1,326 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender, TrySendError, channel, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::core_graphics::{displays, on_display_reconfigured};
+use crate::events::{Event, get_socket_path};
+use crate::geometry::Rect;
+use crate::hooks::Hooks;
+use crate::hotplug::Hotplug;
+use crate::overlay::{self, FocusBorder};
+use crate::run_loop;
+use crate::signature;
+use crate::window::get_focused_window;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Events buffered per subscriber before further events are dropped for it.
+const SUBSCRIBER_QUEUE: usize = 64;
+
+struct Subscriber {
+    filter: Vec<String>,
+    tx: SyncSender<String>,
+}
+
+#[derive(Clone, Default)]
+struct Broker {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl Broker {
+    fn subscribe(&self, filter: Vec<String>) -> Receiver<String> {
+        let (tx, rx) = sync_channel(SUBSCRIBER_QUEUE);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push(Subscriber { filter, tx });
+        rx
+    }
+
+    /// Queues `event` for every interested subscriber without waiting. A
+    /// subscriber whose queue is full misses the event; one that hung up is
+    /// dropped.
+    fn publish(&self, event: &Event) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        let name = event.name();
+        self.subscribers.lock().unwrap().retain(|sub| {
+            if !sub.filter.is_empty() && !sub.filter.iter().any(|f| f == name) {
+                return true;
+            }
+            match sub.tx.try_send(line.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+fn handle_client(broker: Broker, published: Sender<Event>, stream: UnixStream) {
+    let mut reader = BufReader::new(&stream);
+    let mut first = String::new();
+    if reader.read_line(&mut first).is_err() {
+        return;
+    }
+    let (verb, rest) = first
+        .trim_end()
+        .split_once(' ')
+        .unwrap_or((first.trim_end(), ""));
+
+    match verb {
+        "publish" => match serde_json::from_str::<Event>(rest) {
+            Ok(event) => {
+                broker.publish(&event);
+                published.send(event).ok();
+            }
+            Err(e) => eprintln!("Ignoring malformed event: {}", e),
+        },
+        "subscribe" => {
+            let filter = rest
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect();
+            let rx = broker.subscribe(filter);
+            let mut stream = &stream;
+            for line in rx {
+                if writeln!(stream, "{}", line).is_err() {
+                    break;
+                }
+            }
+        }
+        _ => eprintln!("Unknown request from client: {}", verb),
+    }
+}
+
+fn diff_displays(old: &[Rect], new: &[Rect]) -> Vec<Event> {
+    let mut events = Vec::new();
+    for (index, rect) in old.iter().enumerate() {
+        if !new.contains(rect) {
+            events.push(Event::DisplayRemoved { index, rect: *rect });
+        }
+    }
+    for (index, rect) in new.iter().enumerate() {
+        if !old.contains(rect) {
+            events.push(Event::DisplayAdded { index, rect: *rect });
+        }
+    }
+    events
+}
+
+/// `vega daemon`: relays events published by vega commands to subscribers
+/// and watches for focus and display changes, running hooks for the latter.
+/// Windows follow displays as they are unplugged and return. With
+/// `border.enabled` it also draws the focus border.
+pub fn run(config: &Config) -> Result<(), String> {
+    let path = get_socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        return Err(format!(
+            "A vega daemon is already listening on {}",
+            path.display()
+        ));
+    }
+
+    let mut border = if config.border.enabled {
+        overlay::init_app();
+        Some(FocusBorder::new(config)?)
+    } else {
+        None
+    };
+
+    fs::remove_file(&path).ok();
+    let listener = UnixListener::bind(&path).map_err(|e| e.to_string())?;
+    println!("Listening on {}", path.display());
+
+    let broker = Broker::default();
+    let mut hooks = Hooks::new(config);
+    let (published_tx, published_rx) = channel();
+
+    let accept_broker = broker.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let broker = accept_broker.clone();
+            let published = published_tx.clone();
+            thread::spawn(move || handle_client(broker, published, stream));
+        }
+    });
+
+    let focused_window = get_focused_window();
+    let mut focused = focused_window.as_ref().map(signature);
+    if let Some(border) = &mut border {
+        border.follow(focused_window);
+    }
+    let display_rects = || displays().into_iter().map(|d| d.bounds).collect::<Vec<_>>();
+    let mut displays = display_rects();
+    let with_border = border.is_some();
+
+    let hotplug = Rc::new(RefCell::new(Hotplug::default()));
+    let reconfiguring = hotplug.clone();
+    on_display_reconfigured(move |begin| reconfiguring.borrow_mut().reconfiguring(begin));
+    let config = config.clone();
+
+    run_loop::every(POLL_INTERVAL, move || {
+        let focused_window = get_focused_window();
+        let now_focused = focused_window.as_ref().map(signature);
+        let mut refollow = now_focused != focused;
+        if now_focused != focused {
+            if let Some((app, title)) = &now_focused {
+                let event = Event::FocusChanged {
+                    app: app.clone(),
+                    title: title.clone(),
+                };
+                broker.publish(&event);
+                hooks.fire(&event);
+            }
+            focused = now_focused;
+        }
+
+        // A retile may have floated or moved the focused window.
+        for event in published_rx.try_iter() {
+            if let Event::WindowTiled { app, title, .. } = event {
+                refollow |= focused.as_ref() == Some(&(app, title));
+            }
+        }
+        if let Some(border) = &mut border
+            && refollow
+        {
+            border.follow(focused_window);
+        }
+
+        let now_displays = display_rects();
+        for event in diff_displays(&displays, &now_displays) {
+            broker.publish(&event);
+            hooks.fire(&event);
+        }
+        displays = now_displays;
+        hotplug
+            .borrow_mut()
+            .settle(&config, &mut hooks, Instant::now());
+    });
+
+    if with_border {
+        overlay::run_app();
+    } else {
+        run_loop::run();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Layout;
+
+    fn mode(name: &str) -> Event {
+        Event::ModeChanged {
+            mode: name.to_string(),
+        }
+    }
+
+    fn layout() -> Event {
+        Event::LayoutChanged {
+            layout: Layout::Monocle,
+        }
+    }
+
+    fn subscribers(broker: &Broker) -> usize {
+        broker.subscribers.lock().unwrap().len()
+    }
+
+    #[test]
+    fn subscribers_only_get_the_events_they_asked_for() {
+        let broker = Broker::default();
+        let modes = broker.subscribe(vec!["mode_changed".to_string()]);
+        let all = broker.subscribe(Vec::new());
+
+        broker.publish(&layout());
+        broker.publish(&mode("resize"));
+
+        let expected = serde_json::to_string(&mode("resize")).unwrap();
+        let received: Vec<_> = all.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1], expected);
+        assert_eq!(modes.try_iter().collect::<Vec<_>>(), [expected]);
+    }
+
+    #[test]
+    fn a_full_queue_drops_events_without_blocking() {
+        let broker = Broker::default();
+        let slow = broker.subscribe(Vec::new());
+        // Blocking here would hang the test rather than fail it.
+        for i in 0..SUBSCRIBER_QUEUE + 10 {
+            broker.publish(&mode(&i.to_string()));
+        }
+
+        let received: Vec<_> = slow.try_iter().collect();
+        assert_eq!(received.len(), SUBSCRIBER_QUEUE);
+        assert_eq!(
+            received.last(),
+            Some(&serde_json::to_string(&mode(&(SUBSCRIBER_QUEUE - 1).to_string())).unwrap())
+        );
+        // Still subscribed, and getting events again now there is room.
+        assert_eq!(subscribers(&broker), 1);
+        broker.publish(&layout());
+        assert_eq!(slow.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn subscribers_that_hang_up_are_removed() {
+        let broker = Broker::default();
+        let gone = broker.subscribe(Vec::new());
+        let filtered = broker.subscribe(vec!["layout_changed".to_string()]);
+        let _kept = broker.subscribe(Vec::new());
+        drop(gone);
+        drop(filtered);
+
+        broker.publish(&mode("resize"));
+        // Only found out once an event is actually sent its way.
+        assert_eq!(subscribers(&broker), 2);
+        broker.publish(&layout());
+        assert_eq!(subscribers(&broker), 1);
+    }
+
+    #[test]
+    fn display_changes_become_events() {
+        let laptop = Rect::new(0.0, 0.0, 1440.0, 900.0);
+        let monitor = Rect::new(1440.0, 0.0, 1920.0, 1080.0);
+        let moved = Rect::new(-1920.0, 0.0, 1920.0, 1080.0);
+
+        assert_eq!(diff_displays(&[laptop], &[laptop]), []);
+        assert_eq!(
+            diff_displays(&[laptop], &[laptop, monitor]),
+            [Event::DisplayAdded {
+                index: 1,
+                rect: monitor
+            }]
+        );
+        assert_eq!(
+            diff_displays(&[laptop, monitor], &[laptop]),
+            [Event::DisplayRemoved {
+                index: 1,
+                rect: monitor
+            }]
+        );
+        // Moving a display reads as one going and another coming.
+        assert_eq!(
+            diff_displays(&[laptop, monitor], &[laptop, moved]),
+            [
+                Event::DisplayRemoved {
+                    index: 1,
+                    rect: monitor
+                },
+                Event::DisplayAdded {
+                    index: 1,
+                    rect: moved
+                },
+            ]
+        );
+    }
+}
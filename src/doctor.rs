@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use crate::core_graphics::displays;
+#[cfg(not(feature = "x11"))]
+use crate::io_kit::{InputMonitoring, input_monitoring_access, request_input_monitoring};
+use crate::window::collect_windows;
+#[cfg(not(feature = "x11"))]
+use crate::window::is_process_trusted;
+#[cfg(feature = "x11")]
+use crate::window::{connection, window_manager_name};
+
+/// Prints what vega can see and which permissions are missing. Returns
+/// `false` when something needs fixing before tiling can work.
+pub fn run(prompt: bool) -> bool {
+    let (mut healthy, trusted) = check_access(prompt);
+
+    let displays = displays();
+    println!("Displays: {}", displays.len());
+    for display in &displays {
+        let rect = display.bounds;
+        let main = if display.main { " (main)" } else { "" };
+        println!(
+            "  {}: {} {}x{} at ({}, {}) @{}x{}",
+            display.index,
+            display.name,
+            rect.width,
+            rect.height,
+            rect.x,
+            rect.y,
+            display.scale,
+            main
+        );
+        if !display.uuid.is_empty() {
+            println!("     UUID {}", display.uuid);
+        }
+    }
+    if displays.is_empty() {
+        healthy = false;
+        println!("  No active displays were reported.");
+    }
+
+    if trusted {
+        let mut per_app: BTreeMap<String, usize> = BTreeMap::new();
+        for window in collect_windows() {
+            *per_app.entry(window.app_name.clone()).or_default() += 1;
+        }
+        println!("Windows: {}", per_app.values().sum::<usize>());
+        for (app, count) in &per_app {
+            println!("  {}: {}", app, count);
+        }
+    } else {
+        println!("Windows: unavailable until vega can reach them");
+    }
+
+    healthy
+}
+
+/// Checks the Accessibility and Input Monitoring permissions. Returns
+/// whether everything is granted and whether vega can read windows.
+#[cfg(not(feature = "x11"))]
+fn check_access(prompt: bool) -> (bool, bool) {
+    let mut healthy = true;
+
+    let trusted = is_process_trusted(prompt);
+    if trusted {
+        println!("Accessibility: granted");
+    } else {
+        healthy = false;
+        println!("Accessibility: missing");
+        println!(
+            "  vega cannot read or move windows. Enable it under System Settings > \
+             Privacy & Security > Accessibility (for the terminal or binary running vega)."
+        );
+        if !prompt {
+            println!("  Run `vega doctor --prompt` to open the system prompt.");
+        }
+    }
+
+    let mut input_monitoring = input_monitoring_access();
+    if prompt && input_monitoring == InputMonitoring::Undetermined {
+        request_input_monitoring();
+        input_monitoring = input_monitoring_access();
+    }
+    match input_monitoring {
+        InputMonitoring::Granted => println!("Input Monitoring: granted"),
+        InputMonitoring::Denied | InputMonitoring::Undetermined => {
+            healthy = false;
+            let status = if input_monitoring == InputMonitoring::Denied {
+                "denied"
+            } else {
+                "not determined"
+            };
+            println!("Input Monitoring: {}", status);
+            println!(
+                "  scout cannot see key presses. Enable it under System Settings > \
+                 Privacy & Security > Input Monitoring (for the terminal or binary running scout)."
+            );
+        }
+    }
+
+    (healthy, trusted)
+}
+
+/// Checks that the X server can be reached and names the window manager.
+/// Returns the same pair as the macOS version.
+#[cfg(feature = "x11")]
+fn check_access(_prompt: bool) -> (bool, bool) {
+    if connection().is_none() {
+        println!("X display: unavailable");
+        println!("  vega cannot reach an X server. Check that DISPLAY is set.");
+        return (false, false);
+    }
+    println!("X display: connected");
+
+    match window_manager_name() {
+        Some(name) => println!("Window manager: {}", name),
+        None => {
+            println!("Window manager: none found");
+            println!(
+                "  Without an EWMH window manager vega moves top-level windows itself, \
+                 and minimized windows are simply unmapped."
+            );
+        }
+    }
+    (true, true)
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::geometry::Rect;
+use crate::layout::Layout;
+
+/// Something vega did or noticed. Subscribers receive one JSON object per
+/// line, tagged by `event`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    LayoutChanged {
+        layout: Layout,
+    },
+    WindowPromoted {
+        app: String,
+        title: String,
+    },
+    WindowTiled {
+        app: String,
+        title: String,
+        rect: Rect,
+    },
+    FocusChanged {
+        app: String,
+        title: String,
+    },
+    DisplayAdded {
+        index: usize,
+        rect: Rect,
+    },
+    DisplayRemoved {
+        index: usize,
+        rect: Rect,
+    },
+    ModeChanged {
+        mode: String,
+    },
+}
+
+pub const EVENT_NAMES: &[&str] = &[
+    "layout_changed",
+    "window_promoted",
+    "window_tiled",
+    "focus_changed",
+    "display_added",
+    "display_removed",
+    "mode_changed",
+];
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::LayoutChanged { .. } => "layout_changed",
+            Event::WindowPromoted { .. } => "window_promoted",
+            Event::WindowTiled { .. } => "window_tiled",
+            Event::FocusChanged { .. } => "focus_changed",
+            Event::DisplayAdded { .. } => "display_added",
+            Event::DisplayRemoved { .. } => "display_removed",
+            Event::ModeChanged { .. } => "mode_changed",
+        }
+    }
+}
+
+pub fn get_socket_path() -> PathBuf {
+    let mut path = env::temp_dir();
+    path.push("vega.sock");
+    path
+}
+
+/// Hands an event to the daemon, if one is running. Never waits on
+/// subscribers; the daemon queues per client.
+pub fn emit(event: &Event) {
+    let Ok(json) = serde_json::to_string(event) else {
+        return;
+    };
+    if let Ok(mut stream) = UnixStream::connect(get_socket_path()) {
+        stream
+            .set_write_timeout(Some(Duration::from_millis(100)))
+            .ok();
+        writeln!(stream, "publish {}", json).ok();
+    }
+}
+
+/// `vega subscribe [event...]`: prints events from the daemon until it goes
+/// away.
+pub fn subscribe(filter: &[String]) -> Result<(), String> {
+    if let Some(unknown) = filter.iter().find(|f| !EVENT_NAMES.contains(&f.as_str())) {
+        return Err(format!(
+            "Unknown event: {} (expected one of {})",
+            unknown,
+            EVENT_NAMES.join(", ")
+        ));
+    }
+
+    let mut stream = UnixStream::connect(get_socket_path()).map_err(|e| {
+        format!(
+            "Could not reach the vega daemon ({}); start it with `vega daemon`",
+            e
+        )
+    })?;
+    writeln!(stream, "subscribe {}", filter.join(",")).map_err(|e| e.to_string())?;
+
+    let stdout = std::io::stdout();
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let mut out = stdout.lock();
+        if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+            // The reading end of our pipe closed (e.g. `vega subscribe | head`).
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::config::Config;
+use crate::core_graphics::warp_mouse;
+use crate::window::{Window, focus_window, focused_window_index, window_at, window_rect};
+use crate::{State, signature, tiled_groups, tiling_area};
+
+/// Focuses `window` on behalf of a vega command, taking the pointer along
+/// when `mouse_follows_focus` is set.
+pub fn focus(config: &Config, window: &Window) {
+    focus_window(window);
+    if config.mouse_follows_focus
+        && let Some(rect) = window_rect(window)
+    {
+        let (x, y) = rect.center();
+        warp_mouse(x, y);
+    }
+}
+
+/// `vega focus-at <x> <y>`: focuses the tiled window under the point, as
+/// scout does for focus-follows-mouse. Nothing happens while another
+/// window, floating, ignored or not vega's at all, is in front there, so
+/// those keep focus.
+pub fn focus_at(
+    config: &Config,
+    x: f64,
+    y: f64,
+    state: &State,
+    windows: &[Window],
+) -> Result<(), String> {
+    // Only the top of each stack is showing.
+    let tops: Vec<Window> = tiled_groups(state, windows, tiling_area(config, state))
+        .into_iter()
+        .filter_map(|tile| tile.into_iter().next())
+        .collect();
+    let Some(window) = window_at(&tops, x, y).map(|i| &tops[i]) else {
+        return Ok(());
+    };
+
+    let focused = focused_window_index(windows).map(|i| signature(&windows[i]));
+    if focused != Some(signature(window)) {
+        focus_window(window);
+    }
+    Ok(())
+}
@@ -1,7 +1,250 @@
-#[derive(Debug, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
     pub x: f64,
     pub y: f64,
     pub width: f64,
     pub height: f64,
 }
+
+impl Rect {
+    #[cfg(test)]
+    pub const fn new(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// `self` shrunk by `amount` on every side.
+    pub fn inset(&self, amount: f64) -> Rect {
+        Rect {
+            x: self.x + amount,
+            y: self.y + amount,
+            width: (self.width - 2.0 * amount).max(0.0),
+            height: (self.height - 2.0 * amount).max(0.0),
+        }
+    }
+
+    /// `self` with `insets` taken off each edge.
+    pub fn inset_by(&self, insets: Insets) -> Rect {
+        Rect {
+            x: self.x + insets.left,
+            y: self.y + insets.top,
+            width: (self.width - insets.left - insets.right).max(0.0),
+            height: (self.height - insets.top - insets.bottom).max(0.0),
+        }
+    }
+
+    /// `self` with every edge moved to the nearest whole pixel at `scale`
+    /// pixels per point. Rects that shared an edge still do.
+    pub fn aligned(&self, scale: f64) -> Rect {
+        let x = snap(self.x, scale);
+        let y = snap(self.y, scale);
+        Rect {
+            x,
+            y,
+            width: snap(self.x + self.width, scale) - x,
+            height: snap(self.y + self.height, scale) - y,
+        }
+    }
+
+    /// The largest rect inside `self` whose edges fall on whole pixels at
+    /// `scale` pixels per point: edges that are off the grid move inward.
+    pub fn aligned_inside(&self, scale: f64) -> Rect {
+        let x = (self.x * scale).ceil() / scale;
+        let y = (self.y * scale).ceil() / scale;
+        Rect {
+            x,
+            y,
+            width: (((self.x + self.width) * scale).floor() / scale - x).max(0.0),
+            height: (((self.y + self.height) * scale).floor() / scale - y).max(0.0),
+        }
+    }
+
+    /// A `width` x `height` rect centred on `self`, shrunk to fit if needed.
+    pub fn centered(&self, width: f64, height: f64) -> Rect {
+        let width = width.min(self.width);
+        let height = height.min(self.height);
+        Rect {
+            x: self.x + (self.width - width) / 2.0,
+            y: self.y + (self.height - height) / 2.0,
+            width,
+            height,
+        }
+    }
+}
+
+/// Space kept clear along each edge of a display, in points.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Insets {
+    pub top: f64,
+    pub bottom: f64,
+    pub left: f64,
+    pub right: f64,
+}
+
+impl Insets {
+    /// The larger of `self` and `other` on each edge.
+    pub fn max(self, other: Insets) -> Insets {
+        Insets {
+            top: self.top.max(other.top),
+            bottom: self.bottom.max(other.bottom),
+            left: self.left.max(other.left),
+            right: self.right.max(other.right),
+        }
+    }
+}
+
+/// `value` rounded to the nearest whole pixel at `scale` pixels per point.
+pub fn snap(value: f64, scale: f64) -> f64 {
+    (value * scale).round() / scale
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            _ => Err(format!(
+                "Unknown direction: {} (expected left, right, up or down)",
+                s
+            )),
+        }
+    }
+}
+
+/// Index of the rect nearest to `rects[from]` in `direction`, among those
+/// lying past its edge on that side, judged by centres.
+pub fn neighbour(rects: &[Rect], from: usize, direction: Direction) -> Option<usize> {
+    let origin = rects[from];
+    let (fx, fy) = origin.center();
+    rects
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != from)
+        .filter(|(_, rect)| match direction {
+            Direction::Left => rect.x + rect.width <= origin.x,
+            Direction::Right => rect.x >= origin.x + origin.width,
+            Direction::Up => rect.y + rect.height <= origin.y,
+            Direction::Down => rect.y >= origin.y + origin.height,
+        })
+        .map(|(i, rect)| {
+            let (cx, cy) = rect.center();
+            let (dx, dy) = (cx - fx, cy - fy);
+            let (along, across) = match direction {
+                Direction::Left | Direction::Right => (dx.abs(), dy),
+                Direction::Up | Direction::Down => (dy.abs(), dx),
+            };
+            (i, along + across.abs())
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insets(top: f64, bottom: f64, left: f64, right: f64) -> Insets {
+        Insets {
+            top,
+            bottom,
+            left,
+            right,
+        }
+    }
+
+    #[test]
+    fn insets_shrink_each_edge() {
+        let screen = Rect::new(0.0, 0.0, 1440.0, 900.0);
+        assert_eq!(
+            screen.inset_by(insets(25.0, 70.0, 0.0, 10.0)),
+            Rect::new(0.0, 25.0, 1430.0, 805.0)
+        );
+        assert_eq!(screen.inset_by(Insets::default()), screen);
+    }
+
+    #[test]
+    fn oversized_insets_leave_an_empty_rect() {
+        let screen = Rect::new(100.0, 50.0, 200.0, 100.0);
+        assert_eq!(
+            screen.inset_by(insets(80.0, 80.0, 150.0, 150.0)),
+            Rect::new(250.0, 130.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn a_reserve_only_counts_where_it_exceeds_the_system_insets() {
+        // Menu bar on top, Dock at the bottom.
+        let system = insets(25.0, 70.0, 0.0, 0.0);
+        // A bar of 32 on top and a 10 point margin all round.
+        let reserve = insets(32.0, 10.0, 10.0, 10.0);
+        assert_eq!(system.max(reserve), insets(32.0, 70.0, 10.0, 10.0));
+        assert_eq!(reserve.max(system), system.max(reserve));
+
+        let screen = Rect::new(0.0, 0.0, 1440.0, 900.0);
+        assert_eq!(
+            screen.inset_by(system.max(reserve)),
+            Rect::new(10.0, 32.0, 1420.0, 798.0)
+        );
+    }
+
+    /// A master on the left, two stacked on the right and one more below.
+    fn tiles() -> Vec<Rect> {
+        vec![
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+            Rect::new(100.0, 0.0, 100.0, 50.0),
+            Rect::new(100.0, 50.0, 100.0, 50.0),
+            Rect::new(0.0, 100.0, 200.0, 50.0),
+        ]
+    }
+
+    #[test]
+    fn neighbours_in_each_direction() {
+        let tiles = tiles();
+        assert_eq!(neighbour(&tiles, 0, Direction::Right), Some(1));
+        assert_eq!(neighbour(&tiles, 0, Direction::Down), Some(3));
+        assert_eq!(neighbour(&tiles, 1, Direction::Left), Some(0));
+        assert_eq!(neighbour(&tiles, 1, Direction::Down), Some(2));
+        assert_eq!(neighbour(&tiles, 2, Direction::Up), Some(1));
+        assert_eq!(neighbour(&tiles, 2, Direction::Left), Some(0));
+        // Both 0 and 2 lie above; 2's centre is nearer.
+        assert_eq!(neighbour(&tiles, 3, Direction::Up), Some(2));
+    }
+
+    #[test]
+    fn nothing_lies_past_an_edge() {
+        let tiles = tiles();
+        assert_eq!(neighbour(&tiles, 0, Direction::Left), None);
+        assert_eq!(neighbour(&tiles, 0, Direction::Up), None);
+        assert_eq!(neighbour(&tiles, 1, Direction::Right), None);
+        assert_eq!(neighbour(&tiles, 3, Direction::Down), None);
+        assert_eq!(neighbour(&tiles[..1], 0, Direction::Right), None);
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::State;
+
+/// Past and undone arrangements, most recent last.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    undo: Vec<State>,
+    redo: Vec<State>,
+}
+
+impl History {
+    /// Remembers `before` as the arrangement a command replaced, forgetting
+    /// the oldest beyond `limit` and anything that could be redone.
+    pub fn record(&mut self, before: State, limit: usize) {
+        self.undo.push(before);
+        if self.undo.len() > limit {
+            let excess = self.undo.len() - limit;
+            self.undo.drain(..excess);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, current: State) -> Option<State> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: State) -> Option<State> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+}
+
+/// `past` as undo or redo brings it back. Reserves belong to whatever bars
+/// are running and parked windows to the displays that went away, not to
+/// the arrangement, so those stay as they are in `current`.
+pub fn restore(current: &State, past: State) -> State {
+    State {
+        reserves: current.reserves.clone(),
+        unplugged: current.unplugged.clone(),
+        focused: current.focused.clone(),
+        ..past
+    }
+}
+
+fn get_history_file_path() -> PathBuf {
+    let mut path = env::temp_dir();
+    path.push("vega_history.json");
+    path
+}
+
+pub fn load_history() -> History {
+    let path = get_history_file_path();
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_history(history: &History) {
+    let path = get_history_file_path();
+    if let Ok(content) = serde_json::to_string(history) {
+        fs::write(path, content).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Insets, Rect};
+
+    /// A state told apart by its one window.
+    fn state(app: &str) -> State {
+        State {
+            window_order: vec![(app.to_string(), String::new())],
+            ..State::default()
+        }
+    }
+
+    #[test]
+    fn restoring_keeps_reserves_and_parked_windows() {
+        let past = state("A");
+        let mut current = state("B");
+        current
+            .reserves
+            .insert("MONITOR".to_string(), Insets::default());
+        current.unplugged.insert(
+            "MONITOR".to_string(),
+            vec![(
+                ("Mail".to_string(), String::new()),
+                Rect::new(0.0, 0.0, 800.0, 600.0),
+            )],
+        );
+        current.focused = Some(("B".to_string(), String::new()));
+
+        let restored = restore(&current, past.clone());
+        assert_eq!(restored.window_order, past.window_order);
+        assert_eq!(restored.reserves, current.reserves);
+        assert_eq!(restored.unplugged, current.unplugged);
+        assert_eq!(restored.focused, current.focused);
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = History::default();
+        history.record(state("A"), 10);
+        history.record(state("B"), 10);
+
+        assert_eq!(history.undo(state("C")), Some(state("B")));
+        assert_eq!(history.undo(state("B")), Some(state("A")));
+        assert_eq!(history.redo(state("A")), Some(state("B")));
+        assert_eq!(history.redo(state("B")), Some(state("C")));
+        assert_eq!(history.redo(state("C")), None);
+        assert_eq!(history.undo(state("C")), Some(state("B")));
+    }
+
+    #[test]
+    fn an_empty_history_has_nothing_to_undo() {
+        let mut history = History::default();
+        assert_eq!(history.undo(state("A")), None);
+        assert_eq!(history.redo(state("A")), None);
+        // Nothing was moved over by the failed attempts.
+        assert!(history.undo.is_empty() && history.redo.is_empty());
+    }
+
+    #[test]
+    fn recording_clears_what_could_be_redone() {
+        let mut history = History::default();
+        history.record(state("A"), 10);
+        assert_eq!(history.undo(state("B")), Some(state("A")));
+
+        history.record(state("A"), 10);
+        assert_eq!(history.redo(state("C")), None);
+        assert_eq!(history.undo(state("C")), Some(state("A")));
+    }
+
+    #[test]
+    fn the_oldest_states_are_forgotten_beyond_the_limit() {
+        let mut history = History::default();
+        for app in ["A", "B", "C", "D"] {
+            history.record(state(app), 2);
+        }
+        assert_eq!(history.undo.len(), 2);
+        assert_eq!(history.undo(state("E")), Some(state("D")));
+        assert_eq!(history.undo(state("D")), Some(state("C")));
+        assert_eq!(history.undo(state("C")), None);
+
+        history.record(state("A"), 0);
+        assert!(history.undo.is_empty());
+    }
+}
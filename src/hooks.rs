@@ -0,0 +1,243 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::os::raw::c_int;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::events::{EVENT_NAMES, Event};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const SIGKILL: c_int = 9;
+
+unsafe extern "C" {
+    fn killpg(pgrp: c_int, sig: c_int) -> c_int;
+}
+
+/// Internal command that runs one hook under a timeout. Hooks are started
+/// through it so vega can return (and scout can move on) right away.
+pub const SUPERVISE_COMMAND: &str = "__run-hook";
+
+/// Starts the configured shell hooks for events in the background.
+pub struct Hooks {
+    commands: HashMap<String, String>,
+    timeout_ms: u64,
+    supervisors: Vec<Child>,
+}
+
+impl Hooks {
+    pub fn new(config: &Config) -> Self {
+        for name in config.hooks.keys() {
+            if !EVENT_NAMES.contains(&name.as_str()) {
+                eprintln!(
+                    "Ignoring hook for unknown event {} (expected one of {})",
+                    name,
+                    EVENT_NAMES.join(", ")
+                );
+            }
+        }
+        Hooks {
+            commands: config.hooks.clone(),
+            timeout_ms: config.hook_timeout_ms,
+            supervisors: Vec::new(),
+        }
+    }
+
+    pub fn fire(&mut self, event: &Event) {
+        // Reap supervisors that have exited, so a long-running daemon does
+        // not collect zombies.
+        self.supervisors
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let name = event.name();
+        let Some(command) = self.commands.get(name) else {
+            return;
+        };
+        let Ok(exe) = env::current_exe() else {
+            return;
+        };
+
+        let supervisor = Command::new(exe)
+            .arg(SUPERVISE_COMMAND)
+            .arg(self.timeout_ms.to_string())
+            .arg(command)
+            .envs(event_env(event))
+            .stdin(Stdio::null())
+            .spawn();
+        match supervisor {
+            Ok(child) => self.supervisors.push(child),
+            Err(e) => eprintln!("Could not run {} hook: {}", name, e),
+        }
+    }
+}
+
+/// Runs `command` with `sh -c`, killing it and everything it started after
+/// `timeout_ms`, or never when it is 0. Returns the hook's exit code, or 124
+/// on timeout like `timeout(1)`.
+pub fn supervise(timeout_ms: u64, command: &str) -> i32 {
+    // In a process group of its own, so a timeout reaches the commands the
+    // shell runs and not just the shell.
+    let spawned = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .process_group(0)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("Could not run hook {}: {}", command, e);
+            return 127;
+        }
+    };
+
+    if timeout_ms == 0 {
+        return match child.wait() {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(_) => 1,
+        };
+    }
+
+    let timeout = Duration::from_millis(timeout_ms);
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.code().unwrap_or(1),
+            Err(_) => return 1,
+            Ok(None) if started.elapsed() >= timeout => {
+                eprintln!("Killing hook after {:?}: {}", timeout, command);
+                if unsafe { killpg(child.id() as c_int, SIGKILL) } != 0 {
+                    child.kill().ok();
+                }
+                child.wait().ok();
+                return 124;
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Environment for a hook: `VEGA_EVENT` plus one `VEGA_<FIELD>` per event
+/// field, with nested fields joined by `_` (e.g. `VEGA_RECT_WIDTH`).
+fn event_env(event: &Event) -> Vec<(String, String)> {
+    let mut env = vec![("VEGA_EVENT".to_string(), event.name().to_string())];
+    if let Ok(Value::Object(fields)) = serde_json::to_value(event) {
+        for (key, value) in fields {
+            if key != "event" {
+                flatten(&format!("VEGA_{}", key.to_uppercase()), &value, &mut env);
+            }
+        }
+    }
+    env
+}
+
+fn flatten(prefix: &str, value: &Value, env: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                flatten(&format!("{}_{}", prefix, key.to_uppercase()), value, env);
+            }
+        }
+        Value::String(s) => env.push((prefix.to_string(), s.clone())),
+        other => env.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::layout::Layout;
+
+    fn sorted(mut env: Vec<(String, String)>) -> Vec<(String, String)> {
+        env.sort();
+        env
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        sorted(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn nested_fields_are_joined() {
+        let event = Event::WindowTiled {
+            app: "Zed".to_string(),
+            title: "main.rs".to_string(),
+            rect: Rect::new(0.0, 25.0, 720.5, 875.0),
+        };
+        assert_eq!(
+            sorted(event_env(&event)),
+            pairs(&[
+                ("VEGA_EVENT", "window_tiled"),
+                ("VEGA_APP", "Zed"),
+                ("VEGA_TITLE", "main.rs"),
+                ("VEGA_RECT_X", "0.0"),
+                ("VEGA_RECT_Y", "25.0"),
+                ("VEGA_RECT_WIDTH", "720.5"),
+                ("VEGA_RECT_HEIGHT", "875.0"),
+            ])
+        );
+    }
+
+    #[test]
+    fn strings_are_passed_without_quotes() {
+        let event = Event::LayoutChanged {
+            layout: Layout::ALL[0],
+        };
+        let env = sorted(event_env(&event));
+        let layout = serde_json::to_value(Layout::ALL[0]).unwrap();
+        assert_eq!(
+            env,
+            pairs(&[
+                ("VEGA_EVENT", "layout_changed"),
+                ("VEGA_LAYOUT", layout.as_str().unwrap()),
+            ])
+        );
+    }
+
+    #[test]
+    fn flatten_names_every_leaf() {
+        let value = serde_json::json!({
+            "a": { "b": { "c": 1 } },
+            "flag": true,
+            "none": null,
+            "list": [1, 2],
+        });
+        let mut env = Vec::new();
+        flatten("VEGA", &value, &mut env);
+        assert_eq!(
+            sorted(env),
+            pairs(&[
+                ("VEGA_A_B_C", "1"),
+                ("VEGA_FLAG", "true"),
+                ("VEGA_NONE", "null"),
+                ("VEGA_LIST", "[1,2]"),
+            ])
+        );
+    }
+
+    #[test]
+    fn a_zero_timeout_never_kills_the_hook() {
+        assert_eq!(supervise(0, "sleep 0.2; exit 3"), 3);
+        assert_eq!(supervise(5000, "exit 3"), 3);
+    }
+
+    #[test]
+    fn timeouts_kill_the_whole_hook() {
+        let marker = env::temp_dir().join(format!("vega-hook-test-{}", std::process::id()));
+        let command = format!("(sleep 1; touch {}) & wait", marker.display());
+        assert_eq!(supervise(100, &command), 124);
+        thread::sleep(Duration::from_millis(1500));
+        let outlived = marker.exists();
+        std::fs::remove_file(&marker).ok();
+        assert!(!outlived, "the hook's child outlived the timeout");
+    }
+}
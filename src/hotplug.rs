@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::core_graphics::{Display, displays};
+use crate::geometry::Rect;
+use crate::hooks::Hooks;
+use crate::window::{Window, collect_windows, move_and_resize_window, window_rect};
+use crate::{WindowSignature, load_state, order_windows, retile_windows, save_state, signature};
+
+/// How often the daemon notes where windows are. Reading every window's
+/// position is a round trip to every app, too slow for the reconfiguration
+/// callback itself.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Displays and window positions as they were before a reconfiguration.
+struct Snapshot {
+    displays: Vec<Display>,
+    windows: Vec<(WindowSignature, Rect)>,
+}
+
+impl Snapshot {
+    fn take() -> Self {
+        Snapshot {
+            displays: identified_displays(),
+            windows: collect_windows()
+                .iter()
+                .filter_map(|w| Some((signature(w), window_rect(w)?)))
+                .collect(),
+        }
+    }
+}
+
+/// Displays that can be told apart across reconnections.
+fn identified_displays() -> Vec<Display> {
+    displays()
+        .into_iter()
+        .filter(|display| !display.uuid.is_empty())
+        .collect()
+}
+
+/// What a reconfiguration calls for.
+#[derive(Debug, PartialEq)]
+struct Plan {
+    /// Windows of each display that went away, by display UUID, with rects
+    /// relative to the display, which may return somewhere else.
+    parked: Vec<(String, Vec<(WindowSignature, Rect)>)>,
+    /// The tiling order with parked windows moved after the rest.
+    order: Vec<WindowSignature>,
+    /// UUIDs of displays that came back with windows to restore.
+    returned: Vec<String>,
+    /// Windows to put back on returned displays, in global coordinates.
+    restore: Vec<(WindowSignature, Rect)>,
+}
+
+fn plan(
+    before: &Snapshot,
+    after: &[Display],
+    order: &[WindowSignature],
+    unplugged: &HashMap<String, Vec<(WindowSignature, Rect)>>,
+) -> Plan {
+    let mut plan = Plan {
+        parked: Vec::new(),
+        order: order.to_vec(),
+        returned: Vec::new(),
+        restore: Vec::new(),
+    };
+
+    for display in &before.displays {
+        if after.iter().any(|d| d.uuid == display.uuid) {
+            continue;
+        }
+        let rect = display.bounds;
+        let parked: Vec<(WindowSignature, Rect)> = before
+            .windows
+            .iter()
+            .filter(|(_, r)| {
+                let (cx, cy) = r.center();
+                rect.contains(cx, cy)
+            })
+            .map(|(sig, r)| {
+                let offset = Rect {
+                    x: r.x - rect.x,
+                    y: r.y - rect.y,
+                    ..*r
+                };
+                (sig.clone(), offset)
+            })
+            .collect();
+        if parked.is_empty() {
+            continue;
+        }
+        let (moved, mut kept): (Vec<_>, Vec<_>) = plan
+            .order
+            .into_iter()
+            .partition(|sig| parked.iter().any(|(s, _)| s == sig));
+        kept.extend(moved);
+        plan.order = kept;
+        plan.parked.push((display.uuid.clone(), parked));
+    }
+
+    for display in after {
+        if before.displays.iter().any(|d| d.uuid == display.uuid) {
+            continue;
+        }
+        let Some(parked) = unplugged.get(&display.uuid) else {
+            continue;
+        };
+        let rect = display.bounds;
+        plan.returned.push(display.uuid.clone());
+        plan.restore.extend(parked.iter().map(|(sig, r)| {
+            let restored = Rect {
+                x: rect.x + r.x,
+                y: rect.y + r.y,
+                ..*r
+            };
+            (sig.clone(), restored)
+        }));
+    }
+
+    plan
+}
+
+/// Follows displays being plugged in and out from the daemon. Windows on a
+/// display that goes away are tiled after those already on the main
+/// display, and their places are remembered by display UUID so they can go
+/// back when it returns.
+#[derive(Default)]
+pub struct Hotplug {
+    /// The latest snapshot, refreshed from the poll.
+    seen: Option<(Instant, Snapshot)>,
+    /// The snapshot from before the reconfiguration under way.
+    before: Option<Snapshot>,
+    changed: bool,
+}
+
+impl Hotplug {
+    /// Handles a reconfiguration callback: see
+    /// [`crate::core_graphics::on_display_reconfigured`]. Only keeps the
+    /// latest snapshot, so the callback returns at once.
+    pub fn reconfiguring(&mut self, begin: bool) {
+        if begin {
+            if self.before.is_none() {
+                self.before = self.seen.take().map(|(_, snapshot)| snapshot);
+            }
+        } else {
+            self.changed = true;
+        }
+    }
+
+    /// Rearranges windows once a reconfiguration has finished, so the
+    /// callbacks of one change are handled together, and otherwise keeps
+    /// the snapshot fresh. Called from the daemon's poll.
+    pub fn settle(&mut self, config: &Config, hooks: &mut Hooks, now: Instant) {
+        if self.changed {
+            self.changed = false;
+            if let Some(before) = self.before.take() {
+                rearrange(&before, config, hooks);
+            }
+            self.seen = None;
+        }
+        let stale = self
+            .seen
+            .as_ref()
+            .is_none_or(|(taken, _)| now.duration_since(*taken) >= SNAPSHOT_INTERVAL);
+        if stale && self.before.is_none() {
+            self.seen = Some((now, Snapshot::take()));
+        }
+    }
+}
+
+fn rearrange(before: &Snapshot, config: &Config, hooks: &mut Hooks) {
+    let after = identified_displays();
+    let mut state = load_state();
+    let windows: Vec<Window> = collect_windows()
+        .into_iter()
+        .filter(|w| !config.ignores(&w.app_name, &w.title))
+        .collect();
+    let ordered_windows =
+        order_windows(config, &state.window_order, windows, state.focused.as_ref());
+    let order: Vec<WindowSignature> = ordered_windows.iter().map(signature).collect();
+
+    let plan = plan(before, &after, &order, &state.unplugged);
+    let name = |uuid: &str| {
+        before
+            .displays
+            .iter()
+            .chain(&after)
+            .find(|d| d.uuid == uuid && !d.name.is_empty())
+            .map_or(uuid.to_string(), |d| d.name.clone())
+    };
+    for (uuid, parked) in plan.parked {
+        println!(
+            "Display {} removed; moving its {} windows",
+            name(&uuid),
+            parked.len()
+        );
+        state.unplugged.insert(uuid, parked);
+    }
+    for uuid in &plan.returned {
+        println!("Display {} returned; restoring its windows", name(uuid));
+        state.unplugged.remove(uuid);
+    }
+    for (sig, rect) in &plan.restore {
+        if let Some(window) = ordered_windows.iter().find(|w| signature(w) == *sig) {
+            move_and_resize_window(window, *rect);
+        }
+    }
+    // Windows closed while their display was away will not be back.
+    for parked in state.unplugged.values_mut() {
+        parked.retain(|(sig, _)| order.contains(sig));
+    }
+    state.unplugged.retain(|_, parked| !parked.is_empty());
+
+    let ordered_windows = order_windows(config, &plan.order, ordered_windows, None);
+    retile_windows(config, &state, &ordered_windows, hooks);
+    state.window_order = plan.order;
+    save_state(&state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(app: &str) -> WindowSignature {
+        (app.to_string(), String::new())
+    }
+
+    fn laptop() -> Display {
+        Display::new(0, "LAPTOP", "", Rect::new(0.0, 0.0, 1440.0, 900.0))
+    }
+
+    fn monitor_at(x: f64) -> Display {
+        Display::new(1, "MONITOR", "", Rect::new(x, 0.0, 1920.0, 1080.0))
+    }
+
+    fn docked() -> Snapshot {
+        Snapshot {
+            displays: vec![laptop(), monitor_at(1440.0)],
+            windows: vec![
+                (sig("Zed"), Rect::new(0.0, 0.0, 1440.0, 900.0)),
+                (sig("Safari"), Rect::new(1540.0, 100.0, 800.0, 600.0)),
+                (sig("Mail"), Rect::new(2400.0, 50.0, 900.0, 1000.0)),
+            ],
+        }
+    }
+
+    #[test]
+    fn unplugging_parks_windows_relative_to_their_display() {
+        let order = vec![sig("Safari"), sig("Zed"), sig("Mail")];
+        let plan = plan(&docked(), &[laptop()], &order, &HashMap::new());
+
+        assert_eq!(
+            plan.parked,
+            vec![(
+                "MONITOR".to_string(),
+                vec![
+                    (sig("Safari"), Rect::new(100.0, 100.0, 800.0, 600.0)),
+                    (sig("Mail"), Rect::new(960.0, 50.0, 900.0, 1000.0)),
+                ]
+            )]
+        );
+        // The laptop's window keeps master; the newcomers follow in order.
+        assert_eq!(plan.order, vec![sig("Zed"), sig("Safari"), sig("Mail")]);
+        assert!(plan.restore.is_empty());
+    }
+
+    #[test]
+    fn replugging_restores_windows_at_the_new_origin() {
+        let order = vec![sig("Zed"), sig("Safari"), sig("Mail")];
+        let unplugged = plan(&docked(), &[laptop()], &order, &HashMap::new())
+            .parked
+            .into_iter()
+            .collect();
+
+        let undocked = Snapshot {
+            displays: vec![laptop()],
+            windows: Vec::new(),
+        };
+        // Plugged back in, now to the left of the laptop.
+        let after = [laptop(), monitor_at(-1920.0)];
+        let plan = plan(&undocked, &after, &order, &unplugged);
+
+        assert_eq!(plan.returned, vec!["MONITOR".to_string()]);
+        assert_eq!(
+            plan.restore,
+            vec![
+                (sig("Safari"), Rect::new(-1820.0, 100.0, 800.0, 600.0)),
+                (sig("Mail"), Rect::new(-960.0, 50.0, 900.0, 1000.0)),
+            ]
+        );
+        assert_eq!(plan.order, order);
+        assert!(plan.parked.is_empty());
+    }
+
+    #[test]
+    fn an_empty_display_leaves_nothing_behind() {
+        let before = Snapshot {
+            displays: vec![laptop(), monitor_at(1440.0)],
+            windows: vec![(sig("Zed"), Rect::new(0.0, 0.0, 1440.0, 900.0))],
+        };
+        let order = vec![sig("Zed")];
+        let unplugged = plan(&before, &[laptop()], &order, &HashMap::new());
+        assert_eq!(
+            unplugged,
+            Plan {
+                parked: Vec::new(),
+                order: order.clone(),
+                returned: Vec::new(),
+                restore: Vec::new(),
+            }
+        );
+
+        let before = Snapshot {
+            displays: vec![laptop()],
+            ..before
+        };
+        let replugged = plan(
+            &before,
+            &[laptop(), monitor_at(1440.0)],
+            &order,
+            &HashMap::new(),
+        );
+        assert!(replugged.returned.is_empty() && replugged.restore.is_empty());
+    }
+}
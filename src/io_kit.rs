@@ -0,0 +1,36 @@
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {}
+
+type IOHIDRequestType = u32;
+type IOHIDAccessType = u32;
+
+const KIOHID_REQUEST_TYPE_LISTEN_EVENT: IOHIDRequestType = 1;
+
+const KIOHID_ACCESS_TYPE_GRANTED: IOHIDAccessType = 0;
+const KIOHID_ACCESS_TYPE_DENIED: IOHIDAccessType = 1;
+
+unsafe extern "C" {
+    fn IOHIDCheckAccess(request_type: IOHIDRequestType) -> IOHIDAccessType;
+    fn IOHIDRequestAccess(request_type: IOHIDRequestType) -> bool;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputMonitoring {
+    Granted,
+    Denied,
+    Undetermined,
+}
+
+pub fn input_monitoring_access() -> InputMonitoring {
+    match unsafe { IOHIDCheckAccess(KIOHID_REQUEST_TYPE_LISTEN_EVENT) } {
+        KIOHID_ACCESS_TYPE_GRANTED => InputMonitoring::Granted,
+        KIOHID_ACCESS_TYPE_DENIED => InputMonitoring::Denied,
+        _ => InputMonitoring::Undetermined,
+    }
+}
+
+/// Asks macOS to show the Input Monitoring prompt. Only has an effect while
+/// the access is still undetermined.
+pub fn request_input_monitoring() -> bool {
+    unsafe { IOHIDRequestAccess(KIOHID_REQUEST_TYPE_LISTEN_EVENT) }
+}
@@ -1,30 +1,103 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-use crate::geometry::Rect;
-use crate::window;
-use crate::window::move_and_resize_window;
+use crate::WindowSignature;
+use crate::geometry::{Rect, snap};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Layout {
+    #[serde(alias = "vertical")]
     Vertical,
+    #[serde(alias = "horizontal")]
     Horizontal,
+    #[serde(alias = "monocle")]
     Monocle,
 }
 
-pub fn tile_vertical(rects: &mut [Rect], screen: Rect) {
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Vertical, Layout::Horizontal, Layout::Monocle];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Vertical => "vertical",
+            Layout::Horizontal => "horizontal",
+            Layout::Monocle => "monocle",
+        }
+    }
+
+    /// The layout after (or with `reverse`, before) `self` in `cycle`. A
+    /// layout missing from `cycle` moves to its first (or last) entry.
+    pub fn step(self, cycle: &[Layout], reverse: bool) -> Layout {
+        let cycle = if cycle.is_empty() {
+            &Layout::ALL[..]
+        } else {
+            cycle
+        };
+        let len = cycle.len();
+        match cycle.iter().position(|&l| l == self) {
+            Some(i) if reverse => cycle[(i + len - 1) % len],
+            Some(i) => cycle[(i + 1) % len],
+            None if reverse => cycle[len - 1],
+            None => cycle[0],
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Layout::ALL
+            .into_iter()
+            .find(|l| l.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Layout::ALL.iter().map(|l| l.name()).collect();
+                format!(
+                    "Unknown layout: {} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Splits the span from `start` to `start + length` into `parts` runs of
+/// whole pixels at `scale` pixels per point, returning each run's start
+/// and length in points. Pixels that do not divide evenly go one apiece to
+/// the first runs, and each run starts exactly where the last one ended.
+pub fn split(start: f64, length: f64, parts: usize, scale: f64) -> Vec<(f64, f64)> {
+    let first = (start * scale).round() as i64;
+    let total = ((start + length) * scale).round() as i64 - first;
+    let parts = parts as i64;
+    let (base, extra) = (total / parts.max(1), total % parts.max(1));
+
+    let mut edge = first;
+    (0..parts)
+        .map(|i| {
+            let size = base + i64::from(i < extra);
+            let run = (edge as f64 / scale, size as f64 / scale);
+            edge += size;
+            run
+        })
+        .collect()
+}
+
+pub fn tile_vertical(rects: &mut [Rect], screen: Rect, scale: f64) {
     let n = rects.len();
     if n == 0 {
         return;
     }
 
+    let screen = screen.aligned_inside(scale);
     if n == 1 {
         rects[0] = screen;
         return;
     }
 
     let master_ratio = 0.6;
-    let master_width = screen.width * master_ratio;
+    let master_width = snap(screen.width * master_ratio, scale);
     let stack_width = screen.width - master_width;
+    let rows = split(screen.y, screen.height, n - 1, scale);
 
     for (i, rect) in rects.iter_mut().enumerate() {
         if i == 0 {
@@ -37,31 +110,33 @@ pub fn tile_vertical(rects: &mut [Rect], screen: Rect) {
             };
         } else {
             // Stack windows
-            let stack_height = screen.height / (n as f64 - 1.0);
+            let (y, height) = rows[i - 1];
             *rect = Rect {
                 x: screen.x + master_width,
-                y: screen.y + stack_height * (i as f64 - 1.0),
+                y,
                 width: stack_width,
-                height: stack_height,
+                height,
             };
         }
     }
 }
 
-pub fn tile_horizontal(rects: &mut [Rect], screen: Rect) {
+pub fn tile_horizontal(rects: &mut [Rect], screen: Rect, scale: f64) {
     let n = rects.len();
     if n == 0 {
         return;
     }
 
+    let screen = screen.aligned_inside(scale);
     if n == 1 {
         rects[0] = screen;
         return;
     }
 
     let master_ratio = 0.6;
-    let master_height = screen.height * master_ratio;
+    let master_height = snap(screen.height * master_ratio, scale);
     let stack_height = screen.height - master_height;
+    let columns = split(screen.x, screen.width, n - 1, scale);
 
     for (i, rect) in rects.iter_mut().enumerate() {
         if i == 0 {
@@ -74,28 +149,28 @@ pub fn tile_horizontal(rects: &mut [Rect], screen: Rect) {
             };
         } else {
             // Stack windows
-            let stack_width = screen.width / (n as f64 - 1.0);
+            let (x, width) = columns[i - 1];
             *rect = Rect {
-                x: screen.x + stack_width * (i as f64 - 1.0),
+                x,
                 y: screen.y + master_height,
-                width: stack_width,
+                width,
                 height: stack_height,
             };
         }
     }
 }
 
-pub fn tile_monocle(rects: &mut [Rect], screen: Rect) {
+pub fn tile_monocle(rects: &mut [Rect], screen: Rect, scale: f64) {
     for rect in rects.iter_mut() {
-        *rect = screen;
+        *rect = screen.aligned_inside(scale);
     }
 }
 
-pub fn tile_windows(layout: Layout, display: Rect, windows: &[window::Window]) {
-    if windows.is_empty() {
-        return;
-    }
-
+/// Computes the rects `layout` assigns to `count` windows on `display`,
+/// in window order, aligned to whole pixels at `scale` pixels per point.
+/// Where `display` itself is off the pixel grid, tiles stop at the last
+/// whole pixel inside it.
+pub fn layout_rects(layout: Layout, display: Rect, count: usize, scale: f64) -> Vec<Rect> {
     let mut rects = vec![
         Rect {
             x: 0.0,
@@ -103,16 +178,439 @@ pub fn tile_windows(layout: Layout, display: Rect, windows: &[window::Window]) {
             width: 0.0,
             height: 0.0
         };
-        windows.len()
+        count
     ];
 
     match layout {
-        Layout::Vertical => tile_vertical(&mut rects, display),
-        Layout::Horizontal => tile_horizontal(&mut rects, display),
-        Layout::Monocle => tile_monocle(&mut rects, display),
+        Layout::Vertical => tile_vertical(&mut rects, display, scale),
+        Layout::Horizontal => tile_horizontal(&mut rects, display, scale),
+        Layout::Monocle => tile_monocle(&mut rects, display, scale),
+    }
+
+    rects
+}
+
+/// Groups windows, given by signature in tiling order, into tiles. Members
+/// of a stack share the tile of whichever comes first in `order`, listed in
+/// stack order so the top window is first.
+pub fn group_tiles(order: &[WindowSignature], stacks: &[Vec<WindowSignature>]) -> Vec<Vec<usize>> {
+    let mut tiles: Vec<Vec<usize>> = Vec::new();
+    let mut placed = vec![false; order.len()];
+
+    for (i, sig) in order.iter().enumerate() {
+        if placed[i] {
+            continue;
+        }
+        let tile = match stacks.iter().find(|stack| stack.contains(sig)) {
+            // Members already placed, by a stack sharing one of them or as
+            // an earlier window with the same signature, are left there.
+            Some(stack) => stack
+                .iter()
+                .filter_map(|member| (0..order.len()).find(|&j| !placed[j] && order[j] == *member))
+                .collect(),
+            None => vec![i],
+        };
+        for &member in &tile {
+            placed[member] = true;
+        }
+        tiles.push(tile);
+    }
+
+    tiles
+}
+
+/// Like [`layout_rects`], but leaves `gap` points between tiles and along
+/// the display edges.
+pub fn gapped_layout_rects(
+    layout: Layout,
+    display: Rect,
+    count: usize,
+    gap: f64,
+    scale: f64,
+) -> Vec<Rect> {
+    let mut rects = layout_rects(layout, display.inset(gap / 2.0), count, scale);
+    for rect in rects.iter_mut() {
+        *rect = rect.inset(gap / 2.0).aligned(scale);
+    }
+    rects
+}
+
+/// Hands each tile's windows to `place` along with the tile's rect and
+/// returns the rects applied, one per tile. Real tiling moves windows with
+/// it; `vega simulate` only records where they would go.
+pub fn tile_windows<W>(
+    layout: Layout,
+    display: Rect,
+    gap: f64,
+    scale: f64,
+    tiles: &[Vec<W>],
+    mut place: impl FnMut(&W, Rect),
+) -> Vec<Rect> {
+    let rects = gapped_layout_rects(layout, display, tiles.len(), gap, scale);
+
+    for (tile, rect) in tiles.iter().zip(rects.iter()) {
+        for window in tile {
+            place(window, *rect);
+        }
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+    use std::{env, fs};
+
+    const EPSILON: f64 = 1e-6;
+
+    /// Ordinary, odd-sized, offset and negative-origin screens.
+    fn screens() -> Vec<Rect> {
+        vec![
+            Rect::new(0.0, 0.0, 1440.0, 900.0),
+            Rect::new(0.0, 25.0, 1512.0, 957.0),
+            Rect::new(1440.0, -180.0, 2560.0, 1415.0),
+            Rect::new(-1921.0, -1079.0, 1921.0, 1079.0),
+            Rect::new(13.0, 7.0, 333.0, 777.0),
+            Rect::new(0.5, 0.5, 101.0, 99.0),
+        ]
+    }
+
+    fn area(rect: &Rect) -> f64 {
+        rect.width * rect.height
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> f64 {
+        let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+        let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+        width.max(0.0) * height.max(0.0)
+    }
+
+    fn inside(rect: &Rect, screen: &Rect) -> bool {
+        rect.x >= screen.x - EPSILON
+            && rect.y >= screen.y - EPSILON
+            && rect.x + rect.width <= screen.x + screen.width + EPSILON
+            && rect.y + rect.height <= screen.y + screen.height + EPSILON
     }
 
-    for (window, rect) in windows.iter().zip(rects.iter()) {
-        move_and_resize_window(window, *rect);
+    fn each_case(mut check: impl FnMut(Layout, Rect, usize, f64, &[Rect])) {
+        for layout in Layout::ALL {
+            for screen in screens() {
+                for scale in [1.0, 2.0] {
+                    for count in 0..=20 {
+                        let rects = layout_rects(layout, screen, count, scale);
+                        check(layout, screen, count, scale, &rects);
+                    }
+                }
+            }
+        }
+    }
+
+    fn on_pixel_grid(rect: &Rect, scale: f64) -> bool {
+        [rect.x, rect.y, rect.x + rect.width, rect.y + rect.height]
+            .iter()
+            .all(|edge| (edge * scale).fract() == 0.0)
+    }
+
+    #[test]
+    fn every_window_gets_a_rect_inside_the_screen() {
+        each_case(|layout, screen, count, _, rects| {
+            assert_eq!(rects.len(), count, "{:?} with {}", layout, count);
+            for r in rects {
+                assert!(
+                    inside(r, &screen),
+                    "{:?} put {:?} off {:?}",
+                    layout,
+                    r,
+                    screen
+                );
+                assert!(r.width > 0.0 && r.height > 0.0, "{:?} gave {:?}", layout, r);
+            }
+        });
+    }
+
+    #[test]
+    fn tiling_layouts_cover_the_screen_without_overlap() {
+        each_case(|layout, screen, count, scale, rects| {
+            if layout == Layout::Monocle || count == 0 {
+                return;
+            }
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    assert!(
+                        overlap(a, b) < EPSILON,
+                        "{:?} with {}: {:?} overlaps {:?}",
+                        layout,
+                        count,
+                        a,
+                        b
+                    );
+                }
+            }
+            // Inside the whole pixels of the screen and disjoint, so equal
+            // area means full cover.
+            let pixels = screen.aligned_inside(scale);
+            let covered: f64 = rects.iter().map(area).sum();
+            assert!(
+                (covered - area(&pixels)).abs() < EPSILON * area(&pixels),
+                "{:?} with {} covers {} of {}",
+                layout,
+                count,
+                covered,
+                area(&pixels)
+            );
+        });
+    }
+
+    #[test]
+    fn monocle_gives_every_window_the_whole_screen() {
+        for screen in screens() {
+            for scale in [1.0, 2.0] {
+                for r in layout_rects(Layout::Monocle, screen, 20, scale) {
+                    assert_eq!(r, screen.aligned_inside(scale));
+                }
+            }
+        }
+        let offset = Rect::new(0.5, 0.5, 101.0, 99.0);
+        assert_eq!(
+            layout_rects(Layout::Monocle, offset, 1, 1.0)[0],
+            Rect::new(1.0, 1.0, 100.0, 98.0)
+        );
+    }
+
+    #[test]
+    fn ordering_is_stable() {
+        each_case(|layout, screen, count, scale, rects| {
+            assert_eq!(rects, layout_rects(layout, screen, count, scale));
+            if count < 2 {
+                return;
+            }
+            // The master keeps its place however many windows join.
+            assert_eq!(rects[0], layout_rects(layout, screen, 2, scale)[0]);
+            // The stack runs down (or across) in window order.
+            for pair in rects[1..].windows(2) {
+                match layout {
+                    Layout::Vertical => assert!(pair[0].y < pair[1].y),
+                    Layout::Horizontal => assert!(pair[0].x < pair[1].x),
+                    Layout::Monocle => assert_eq!(pair[0], pair[1]),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn gaps_keep_tiles_apart_and_on_screen() {
+        for layout in [Layout::Vertical, Layout::Horizontal] {
+            for screen in screens() {
+                for count in 0..=20 {
+                    let rects = gapped_layout_rects(layout, screen, count, 8.0, 2.0);
+                    for (i, a) in rects.iter().enumerate() {
+                        assert!(inside(a, &screen));
+                        for b in &rects[i + 1..] {
+                            assert_eq!(overlap(a, b), 0.0, "{:?} meets {:?}", a, b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn split_hands_leftover_pixels_to_the_first_runs() {
+        assert_eq!(
+            split(0.0, 900.0, 7, 1.0),
+            vec![
+                (0.0, 129.0),
+                (129.0, 129.0),
+                (258.0, 129.0),
+                (387.0, 129.0),
+                (516.0, 128.0),
+                (644.0, 128.0),
+                (772.0, 128.0),
+            ]
+        );
+        assert_eq!(
+            split(25.0, 100.0, 3, 2.0),
+            vec![(25.0, 33.5), (58.5, 33.5), (92.0, 33.0)]
+        );
+    }
+
+    #[test]
+    fn awkward_divisors_leave_no_seams() {
+        for divisor in [3, 6, 7, 11, 13, 17] {
+            for scale in [1.0, 2.0] {
+                for (start, length) in [(0.0, 900.0), (25.0, 875.0), (-1079.0, 1079.0)] {
+                    let runs = split(start, length, divisor, scale);
+                    assert_eq!(runs[0].0, start);
+                    for pair in runs.windows(2) {
+                        assert_eq!(pair[0].0 + pair[0].1, pair[1].0);
+                        assert!((pair[0].1 - pair[1].1).abs() <= 1.0 / scale);
+                    }
+                    let (last, size) = runs[divisor - 1];
+                    assert_eq!(last + size, start + length);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rects_sit_on_the_pixel_grid() {
+        each_case(|layout, _, _, scale, rects| {
+            for r in rects {
+                assert!(on_pixel_grid(r, scale), "{:?} gave {:?}", layout, r);
+            }
+        });
+        for gap in [3.0, 5.0, 7.5] {
+            for r in gapped_layout_rects(Layout::Vertical, screens()[1], 7, gap, 2.0) {
+                assert!(on_pixel_grid(&r, 2.0), "gap {} gave {:?}", gap, r);
+            }
+        }
+    }
+
+    #[test]
+    fn stack_neighbours_share_edges_exactly() {
+        for count in [4, 8] {
+            let rects = layout_rects(Layout::Vertical, screens()[1], count, 1.0);
+            assert_eq!(rects[0].x + rects[0].width, rects[1].x);
+            for pair in rects[1..].windows(2) {
+                assert_eq!(pair[0].y + pair[0].height, pair[1].y);
+            }
+            let rects = layout_rects(Layout::Horizontal, screens()[3], count, 2.0);
+            assert_eq!(rects[0].y + rects[0].height, rects[1].y);
+            for pair in rects[1..].windows(2) {
+                assert_eq!(pair[0].x + pair[0].width, pair[1].x);
+            }
+        }
+    }
+
+    #[test]
+    fn step_walks_the_cycle_both_ways() {
+        use Layout::*;
+        let cycle = [Vertical, Monocle];
+        assert_eq!(Vertical.step(&cycle, false), Monocle);
+        assert_eq!(Monocle.step(&cycle, false), Vertical);
+        assert_eq!(Vertical.step(&cycle, true), Monocle);
+        assert_eq!(Monocle.step(&cycle, true), Vertical);
+        assert_eq!(Vertical.step(&[Vertical], false), Vertical);
+    }
+
+    #[test]
+    fn step_enters_a_cycle_that_omits_the_layout() {
+        use Layout::*;
+        let cycle = [Vertical, Monocle];
+        assert_eq!(Horizontal.step(&cycle, false), Vertical);
+        assert_eq!(Horizontal.step(&cycle, true), Monocle);
+    }
+
+    #[test]
+    fn an_empty_cycle_steps_through_every_layout() {
+        for (i, layout) in Layout::ALL.into_iter().enumerate() {
+            let next = Layout::ALL[(i + 1) % Layout::ALL.len()];
+            assert_eq!(layout.step(&[], false), next);
+            assert_eq!(next.step(&[], true), layout);
+        }
+    }
+
+    #[test]
+    fn layouts_parse_by_name() {
+        for layout in Layout::ALL {
+            assert_eq!(layout.name().parse(), Ok(layout));
+            assert_eq!(layout.name().to_uppercase().parse(), Ok(layout));
+        }
+        assert_eq!(
+            "spiral".parse::<Layout>(),
+            Err(
+                "Unknown layout: spiral (expected one of vertical, horizontal, monocle)"
+                    .to_string()
+            )
+        );
+    }
+
+    fn sigs(apps: &[&str]) -> Vec<WindowSignature> {
+        apps.iter()
+            .map(|app| (app.to_string(), String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn stacks_share_the_tile_of_their_first_window() {
+        let order = sigs(&["A", "B", "C", "D"]);
+        let stacks = [sigs(&["D", "B"])];
+        assert_eq!(group_tiles(&order, &stacks), [vec![0], vec![3, 1], vec![2]]);
+    }
+
+    #[test]
+    fn stale_stack_members_are_skipped() {
+        let order = sigs(&["A", "B"]);
+        let stacks = [sigs(&["Gone", "B"]), sigs(&["Also gone"])];
+        assert_eq!(group_tiles(&order, &stacks), [vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn every_window_is_in_exactly_one_tile() {
+        // B is in two stacks, and there are two windows signed A.
+        let order = sigs(&["A", "B", "C", "A"]);
+        let stacks = [sigs(&["A", "B"]), sigs(&["B", "C"]), sigs(&["A"])];
+        let tiles = group_tiles(&order, &stacks);
+        assert_eq!(tiles, [vec![0, 1], vec![2], vec![3]]);
+
+        let mut members: Vec<usize> = tiles.concat();
+        members.sort_unstable();
+        assert_eq!(members, [0, 1, 2, 3]);
+    }
+
+    /// Renders the rects for a fixed set of cases, one rect per line, for
+    /// comparison against `tests/golden/<layout>.txt`.
+    fn render_golden(layout: Layout) -> String {
+        let mut out = format!(
+            "# {} layout: windows, screen and gap, then rects\n",
+            layout.name()
+        );
+        for screen in [
+            Rect::new(0.0, 0.0, 1440.0, 900.0),
+            Rect::new(-1921.0, 25.0, 1921.0, 1055.0),
+        ] {
+            for gap in [0.0, 10.0] {
+                for count in [1, 2, 3, 7] {
+                    writeln!(
+                        out,
+                        "{} at {},{} {}x{} gap {}",
+                        count, screen.x, screen.y, screen.width, screen.height, gap
+                    )
+                    .unwrap();
+                    for r in gapped_layout_rects(layout, screen, count, gap, 1.0) {
+                        writeln!(out, "  {},{} {}x{}", r.x, r.y, r.width, r.height).unwrap();
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Set `VEGA_UPDATE_GOLDEN=1` to rewrite the golden files after an
+    /// intended change, then review the diff.
+    #[test]
+    fn layouts_match_golden_files() {
+        for layout in Layout::ALL {
+            let path = format!(
+                "{}/tests/golden/{}.txt",
+                env!("CARGO_MANIFEST_DIR"),
+                layout.name()
+            );
+            let actual = render_golden(layout);
+            if env::var_os("VEGA_UPDATE_GOLDEN").is_some() {
+                fs::write(&path, actual).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                expected == actual,
+                "{} no longer matches {}; rerun with VEGA_UPDATE_GOLDEN=1 if intended\n{}",
+                layout.name(),
+                path,
+                actual
+            );
+        }
     }
 }
@@ -5,28 +5,84 @@ use std::fs;
 use std::path::PathBuf;
 
 use crate::{
-    layout::{Layout, tile_windows},
+    config::{Config, load_config},
+    events::Event,
+    geometry::{Insets, Rect},
+    history::{load_history, save_history},
+    hooks::Hooks,
+    layout::{Layout, group_tiles, tile_windows},
     window::{
-        CFRelease, Window, collect_windows, get_focused_window_ref, is_window_minimized,
-        window_rect,
+        Window, collect_windows, focused_window_index, is_process_trusted, is_window_minimized,
+        move_and_resize_window, raise_window, window_rect,
     },
 };
 
+#[cfg(not(any(target_os = "macos", feature = "x11")))]
+compile_error!("vega drives windows through macOS Accessibility or, with the `x11` feature, X11");
+
+#[cfg(not(feature = "x11"))]
+mod border;
+mod config;
+mod config_path;
+#[cfg_attr(feature = "x11", path = "x11/display.rs")]
 mod core_graphics;
+mod daemon;
+mod doctor;
+mod events;
+mod focus;
 mod geometry;
+mod history;
+mod hooks;
+mod hotplug;
+#[cfg(not(feature = "x11"))]
+mod io_kit;
 mod layout;
+mod order;
+#[cfg_attr(feature = "x11", path = "x11/overlay.rs")]
+mod overlay;
+mod query;
+mod reserve;
+#[cfg_attr(feature = "x11", path = "x11/run_loop.rs")]
+mod run_loop;
+mod scratchpad;
+mod session;
+mod simulate;
+mod stack;
+#[cfg_attr(feature = "x11", path = "x11/window.rs")]
 mod window;
 
+#[cfg(not(feature = "x11"))]
 #[link(name = "AppKit", kind = "framework")]
 #[link(name = "ApplicationServices", kind = "framework")]
 unsafe extern "C" {}
 
 type WindowSignature = (String, String);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct State {
     current_layout: Layout,
     window_order: Vec<WindowSignature>,
+    /// Windows left out of tiling, e.g. shown scratchpads.
+    #[serde(default)]
+    floating: Vec<WindowSignature>,
+    /// Scratchpad name to the window it last toggled.
+    #[serde(default)]
+    scratchpads: HashMap<String, WindowSignature>,
+    /// Groups of windows sharing one tile, top window first.
+    #[serde(default)]
+    stacks: Vec<Vec<WindowSignature>>,
+    /// Reserves set with `vega reserve`, keyed by display UUID. They
+    /// replace the configured reserve for that display.
+    #[serde(default)]
+    reserves: HashMap<String, Insets>,
+    /// Windows of displays that were unplugged, keyed by display UUID, with
+    /// rects relative to the display so they can go back when it returns.
+    #[serde(default)]
+    unplugged: HashMap<String, Vec<(WindowSignature, Rect)>>,
+    /// The window focused when vega last ran, which new windows are placed
+    /// next to when they open with focus.
+    #[serde(default)]
+    focused: Option<WindowSignature>,
 }
 
 impl Default for State {
@@ -34,10 +90,39 @@ impl Default for State {
         State {
             current_layout: Layout::Vertical,
             window_order: Vec::new(),
+            floating: Vec::new(),
+            scratchpads: HashMap::new(),
+            stacks: Vec::new(),
+            reserves: HashMap::new(),
+            unplugged: HashMap::new(),
+            focused: None,
         }
     }
 }
 
+fn signature(window: &Window) -> WindowSignature {
+    (window.app_name.clone(), window.title.clone())
+}
+
+/// Arranges live windows by `order`; windows not in it are placed by the
+/// configured order rules and insertion policy, `focused` being the window
+/// the policy places them next to.
+fn order_windows(
+    config: &Config,
+    order: &[WindowSignature],
+    windows: Vec<Window>,
+    focused: Option<&WindowSignature>,
+) -> Vec<Window> {
+    order::merge(
+        order,
+        windows,
+        signature,
+        &config.order,
+        config.new_windows,
+        focused,
+    )
+}
+
 fn get_state_file_path() -> PathBuf {
     let mut path = env::temp_dir();
     path.push("vega_state.json");
@@ -59,50 +144,111 @@ fn save_state(state: &State) {
     }
 }
 
-fn retile_windows(layout: Layout, windows: &[Window]) {
-    let main_display = core_graphics::main_screen_rect();
+/// Whether the tiler manages a window: it must be visible and centred on
+/// `display`.
+fn is_tileable(rect: Option<Rect>, minimized: bool, display: Rect) -> bool {
+    if minimized {
+        return false;
+    }
+    match rect {
+        Some(rect) => {
+            let (cx, cy) = rect.center();
+            display.contains(cx, cy)
+        }
+        None => false,
+    }
+}
 
+/// The part of the main display windows are tiled in: clear of the menu
+/// bar, Dock and notch, and of any space reserved for other bars.
+fn tiling_area(config: &Config, state: &State) -> Rect {
+    let reserve = core_graphics::displays()
+        .iter()
+        .find(|display| display.main)
+        .map_or(config.reserve, |display| {
+            reserve::reserve_for(config, state, display)
+        });
+    core_graphics::main_screen_rect().inset_by(core_graphics::main_screen_insets().max(reserve))
+}
+
+/// Publishes `event` to subscribers and starts its hook, if configured.
+fn announce(hooks: &mut Hooks, event: Event) {
+    events::emit(&event);
+    hooks.fire(&event);
+}
+
+fn switch_layout(
+    config: &Config,
+    state: &mut State,
+    layout: Layout,
+    windows: &[Window],
+    hooks: &mut Hooks,
+) {
+    state.current_layout = layout;
+    println!("Switching to layout: {:?}", state.current_layout);
+
+    retile_windows(config, state, windows, hooks);
+    announce(
+        hooks,
+        Event::LayoutChanged {
+            layout: state.current_layout,
+        },
+    );
+}
+
+/// The windows the tiler manages on the main display, grouped into tiles in
+/// tiling order. Stacked windows share a tile, top window first.
+fn tiled_groups(state: &State, windows: &[Window], display: Rect) -> Vec<Vec<Window>> {
     let filtered_windows: Vec<_> = windows
         .iter()
-        .filter(|w| {
-            if is_window_minimized(w) {
-                return false;
-            }
-            if let Some(rect) = window_rect(w) {
-                let cx = rect.x + rect.width / 2.0;
-                let cy = rect.y + rect.height / 2.0;
-                cx >= main_display.x
-                    && cx < main_display.x + main_display.width
-                    && cy >= main_display.y
-                    && cy < main_display.y + main_display.height
-            } else {
-                false
-            }
-        })
-        .cloned()
+        .filter(|w| !state.floating.contains(&signature(w)))
+        .filter(|w| is_tileable(window_rect(w), is_window_minimized(w), display))
         .collect();
+    let signatures: Vec<_> = filtered_windows.iter().map(|w| signature(w)).collect();
+
+    group_tiles(&signatures, &state.stacks)
+        .into_iter()
+        .map(|tile| {
+            tile.into_iter()
+                .map(|i| filtered_windows[i].clone())
+                .collect()
+        })
+        .collect()
+}
+
+fn retile_windows(config: &Config, state: &State, windows: &[Window], hooks: &mut Hooks) {
+    let layout = state.current_layout;
+    let main_display = tiling_area(config, state);
+
+    let tiles = tiled_groups(state, windows, main_display);
 
     println!(
         "Tiling {} windows using {:?} layout",
-        filtered_windows.len(),
+        tiles.iter().map(Vec::len).sum::<usize>(),
         layout
     );
 
-    tile_windows(layout, main_display, &filtered_windows);
-}
-
-impl From<crate::window::AXUIElementRef> for crate::window::SendableAXUIElementRef {
-    fn from(ax_ref: crate::window::AXUIElementRef) -> Self {
-        Self(ax_ref)
-    }
-}
-
-impl Clone for Window {
-    fn clone(&self) -> Self {
-        Window {
-            ax_ref: unsafe { crate::window::CFRetain(*self.ax_ref) }.into(),
-            app_name: self.app_name.clone(),
-            title: self.title.clone(),
+    let rects = tile_windows(
+        layout,
+        main_display,
+        config.gap,
+        core_graphics::main_screen_scale(),
+        &tiles,
+        move_and_resize_window,
+    );
+    for (tile, rect) in tiles.iter().zip(rects) {
+        if tile.len() > 1 {
+            raise_window(&tile[0]);
+        }
+        for window in tile {
+            announce(
+                hooks,
+                Event::WindowTiled {
+                    app: window.app_name.clone(),
+                    title: window.title.clone(),
+                    rect,
+                },
+            );
         }
     }
 }
@@ -110,68 +256,251 @@ impl Clone for Window {
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: vega <cycle|promote>");
+        eprintln!(
+            "Usage: vega <cycle|layout|promote|stack|stack-cycle|focus-at|scratchpad|session|undo|redo|doctor|query|reserve|daemon|subscribe|mode|simulate>"
+        );
         return;
     }
 
     let command = &args[1];
 
+    match command.as_str() {
+        "doctor" => {
+            let prompt = args.iter().skip(2).any(|a| a == "--prompt");
+            if !doctor::run(prompt) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        hooks::SUPERVISE_COMMAND if args.len() == 4 => {
+            let Ok(timeout_ms) = args[2].parse() else {
+                eprintln!("Invalid hook timeout: {}", args[2]);
+                std::process::exit(1);
+            };
+            std::process::exit(hooks::supervise(timeout_ms, &args[3]));
+        }
+        "daemon" => {
+            if let Err(err) = daemon::run(&load_config()) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        "mode" => {
+            // Run by scout whenever its keybinding mode changes, so status
+            // bars and hooks can follow along.
+            let Some(mode) = args.get(2) else {
+                eprintln!("Usage: vega mode <name>");
+                std::process::exit(1);
+            };
+            let mut hooks = Hooks::new(&load_config());
+            announce(&mut hooks, Event::ModeChanged { mode: mode.clone() });
+            return;
+        }
+        "subscribe" => {
+            if let Err(err) = events::subscribe(&args[2..]) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        "simulate" => {
+            if let Err(err) = simulate::run(&args[2..], &load_config()) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    if !is_process_trusted(false) {
+        eprintln!("vega does not have Accessibility access; run `vega doctor` for details");
+    }
+
+    let config = load_config();
     let mut state = load_state();
-    let all_layouts = [Layout::Vertical, Layout::Horizontal, Layout::Monocle];
+    let mut hooks = Hooks::new(&config);
 
-    let live_windows = collect_windows();
-    let mut live_map: HashMap<WindowSignature, Window> = live_windows
+    let windows: Vec<_> = collect_windows()
         .into_iter()
-        .map(|w| ((w.app_name.clone(), w.title.clone()), w))
-        .collect();
-
-    let mut ordered_windows: Vec<Window> = state
-        .window_order
-        .iter()
-        .filter_map(|sig| live_map.remove(sig))
+        .filter(|w| !config.ignores(&w.app_name, &w.title))
         .collect();
+    let focused = focused_window_index(&windows).map(|i| signature(&windows[i]));
+    // A window that just opened has focus but no place yet, so it goes next
+    // to the one focused when vega last ran.
+    let anchor = focused
+        .clone()
+        .filter(|sig| state.window_order.contains(sig))
+        .or_else(|| state.focused.clone());
+    let mut ordered_windows = order_windows(&config, &state.window_order, windows, anchor.as_ref());
+    if focused.is_some() {
+        state.focused = focused;
+    }
 
-    ordered_windows.extend(live_map.into_values());
+    // The arrangement as it stands before this command, for undo.
+    let mut before = state.clone();
+    before.window_order = ordered_windows.iter().map(signature).collect();
 
     match command.as_str() {
         "cycle" => {
-            let current_index = all_layouts
-                .iter()
-                .position(|&l| l == state.current_layout)
-                .unwrap_or(0);
-
-            let next_index = (current_index + 1) % all_layouts.len();
-            state.current_layout = all_layouts[next_index];
-            println!("Switching to layout: {:?}", state.current_layout);
-
-            retile_windows(state.current_layout, &ordered_windows);
+            let reverse = args.iter().skip(2).any(|a| a == "--reverse");
+            let layout = state.current_layout.step(&config.cycle, reverse);
+            switch_layout(&config, &mut state, layout, &ordered_windows, &mut hooks);
         }
-        "promote" => {
-            if let Some(focused_ref) = get_focused_window_ref() {
-                if let Some(pos) = ordered_windows
-                    .iter()
-                    .position(|w| unsafe { crate::window::CFEqual(*w.ax_ref, *focused_ref) } != 0)
-                {
-                    let master_window = ordered_windows.remove(pos);
-                    println!("Promoting '{}'", master_window.app_name);
-                    ordered_windows.insert(0, master_window);
-
-                    retile_windows(state.current_layout, &ordered_windows);
+        "layout" => {
+            let Some(name) = args.get(2) else {
+                eprintln!("Usage: vega layout <name>");
+                std::process::exit(1);
+            };
+            match name.parse() {
+                Ok(layout) => {
+                    switch_layout(&config, &mut state, layout, &ordered_windows, &mut hooks)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
                 }
+            }
+        }
+        "promote" => {
+            if let Some(pos) = focused_window_index(&ordered_windows) {
+                let master_window = ordered_windows.remove(pos);
+                println!("Promoting '{}'", master_window.app_name);
+                let promoted = Event::WindowPromoted {
+                    app: master_window.app_name.clone(),
+                    title: master_window.title.clone(),
+                };
+                ordered_windows.insert(0, master_window);
 
-                unsafe { CFRelease(*focused_ref) };
+                retile_windows(&config, &state, &ordered_windows, &mut hooks);
+                announce(&mut hooks, promoted);
             } else {
                 println!("Could not find a focused window");
             }
         }
+        "stack" => {
+            let result = match args.get(2) {
+                Some(direction) => direction.parse().and_then(|d| {
+                    stack::join(d, &config, &mut state, &ordered_windows, &mut hooks)
+                }),
+                None => Err("Usage: vega stack <left|right|up|down>".to_string()),
+            };
+            if let Err(err) = result {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        "stack-cycle" => {
+            if let Err(err) = stack::cycle(&config, &mut state, &ordered_windows) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        "focus-at" => {
+            let point = match (args.get(2), args.get(3)) {
+                (Some(x), Some(y)) => x.parse().ok().zip(y.parse().ok()),
+                _ => None,
+            };
+            let Some((x, y)) = point else {
+                eprintln!("Usage: vega focus-at <x> <y>");
+                std::process::exit(1);
+            };
+            if let Err(err) = focus::focus_at(&config, x, y, &state, &ordered_windows) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            // scout runs this on every pointer dwell; saving here could
+            // overwrite what a concurrent command just saved.
+            return;
+        }
+        "scratchpad" => {
+            let result = match (args.get(2).map(String::as_str), args.get(3)) {
+                (Some("toggle"), Some(name)) => {
+                    scratchpad::toggle(name, &config, &mut state, &ordered_windows, &mut hooks)
+                }
+                _ => Err("Usage: vega scratchpad toggle <name>".to_string()),
+            };
+            if let Err(err) = result {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        "session" => {
+            let result = match (args.get(2).map(String::as_str), args.get(3)) {
+                (Some("save"), Some(name)) => session::save(name, &state, &ordered_windows),
+                (Some("restore"), Some(name)) => {
+                    session::restore(name, &config, &mut state, &mut ordered_windows, &mut hooks)
+                }
+                _ => Err("Usage: vega session <save|restore> <name>".to_string()),
+            };
+            if let Err(err) = result {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        "undo" | "redo" => {
+            let mut history = load_history();
+            let restored = if command == "undo" {
+                history.undo(before.clone())
+            } else {
+                history.redo(before.clone())
+            };
+            let Some(restored) = restored else {
+                println!("Nothing to {}", command);
+                return;
+            };
+            save_history(&history);
+
+            println!("Restoring {:?} layout", restored.current_layout);
+            state = history::restore(&before, restored);
+            ordered_windows = order_windows(
+                &config,
+                &state.window_order,
+                ordered_windows,
+                anchor.as_ref(),
+            );
+            retile_windows(&config, &state, &ordered_windows, &mut hooks);
+            if state.current_layout != before.current_layout {
+                announce(
+                    &mut hooks,
+                    Event::LayoutChanged {
+                        layout: state.current_layout,
+                    },
+                );
+            }
+        }
+        "reserve" => {
+            if let Err(err) = reserve::run(&args[2..], &config, &mut state) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            retile_windows(&config, &state, &ordered_windows, &mut hooks);
+        }
+        "query" => {
+            if let Err(err) = query::run(
+                args.get(2).map(String::as_str),
+                &config,
+                &state,
+                &ordered_windows,
+            ) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            // Queries are read-only, so the merged order is not persisted.
+            return;
+        }
         _ => {
             eprintln!("Unknown command: {}", command);
         }
     }
 
-    state.window_order = ordered_windows
-        .iter()
-        .map(|w| (w.app_name.clone(), w.title.clone()))
-        .collect();
+    state.window_order = ordered_windows.iter().map(signature).collect();
+    let recorded = !matches!(command.as_str(), "undo" | "redo" | "reserve");
+    if recorded && state != before {
+        let mut history = load_history();
+        history.record(before, config.history_limit);
+        save_history(&history);
+    }
     save_state(&state);
 }
@@ -0,0 +1,298 @@
+use serde::Deserialize;
+
+use crate::WindowSignature;
+use crate::config::OrderRule;
+
+/// Where new windows join the tiling order among windows of the same
+/// priority.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Insertion {
+    /// At the bottom of the stack.
+    #[default]
+    End,
+    /// As master.
+    Start,
+    /// Right after the focused window, or at the end without one.
+    AfterFocused,
+    /// Right before the focused window, or at the end without one.
+    BeforeFocused,
+}
+
+/// What the order rules say about one window.
+#[derive(Debug, Default, Copy, Clone)]
+struct Placement {
+    priority: i32,
+    slot: Option<isize>,
+}
+
+fn placement(rules: &[OrderRule], signature: &WindowSignature) -> Placement {
+    rules
+        .iter()
+        .find(|r| r.rule.matches(&signature.0, &signature.1))
+        .map(|r| Placement {
+            priority: r.priority,
+            slot: r.slot,
+        })
+        .unwrap_or_default()
+}
+
+/// The index `slot` names in a list of `len` windows: from the start, or
+/// from the end when negative. Out-of-range slots are clamped.
+fn slot_index(slot: isize, len: usize) -> usize {
+    let index = if slot >= 0 {
+        slot.unsigned_abs()
+    } else {
+        len.saturating_sub(slot.unsigned_abs())
+    };
+    index.min(len.saturating_sub(1))
+}
+
+/// Arranges `items` by `order`, then adds those not in it as `rules` and
+/// `insertion` say. Windows in `order` keep their places, except that
+/// slotted windows found in their slot are kept there as new windows join.
+/// New windows that land together go in signature order, so the result
+/// does not depend on the order `items` came in.
+pub fn merge<T>(
+    order: &[WindowSignature],
+    items: Vec<T>,
+    signature: impl Fn(&T) -> WindowSignature,
+    rules: &[OrderRule],
+    insertion: Insertion,
+    focused: Option<&WindowSignature>,
+) -> Vec<T> {
+    let mut pending: Vec<Option<(WindowSignature, T)>> = items
+        .into_iter()
+        .map(|item| Some((signature(&item), item)))
+        .collect();
+
+    let mut merged: Vec<(WindowSignature, T)> = Vec::new();
+    for sig in order {
+        let found = pending
+            .iter_mut()
+            .find(|entry| entry.as_ref().is_some_and(|(s, _)| s == sig));
+        if let Some(entry) = found {
+            merged.extend(entry.take());
+        }
+    }
+
+    // Take out the windows to seat afterwards: those still in their slot
+    // and new slotted ones. The rest of the new windows go by priority.
+    let known = merged.len();
+    let mut seated = Vec::new();
+    for (i, entry) in std::mem::take(&mut merged).into_iter().enumerate() {
+        match placement(rules, &entry.0).slot {
+            Some(slot) if slot_index(slot, known) == i => seated.push((slot, entry)),
+            _ => merged.push(entry),
+        }
+    }
+    let mut joining = Vec::new();
+    for (sig, item) in pending.into_iter().flatten() {
+        match placement(rules, &sig).slot {
+            Some(slot) => seated.push((slot, (sig, item))),
+            None => joining.push((sig, item)),
+        }
+    }
+    // Stable, so identical signatures keep the order they came in.
+    joining.sort_by(|a, b| a.0.cmp(&b.0));
+    seated.sort_by(|a, b| a.1.0.cmp(&b.1.0));
+
+    // Each priority joins as one block, in signature order.
+    let mut priorities: Vec<i32> = joining
+        .iter()
+        .map(|(sig, _)| placement(rules, sig).priority)
+        .collect();
+    priorities.sort_unstable();
+    priorities.dedup();
+    for priority in priorities {
+        let (block, rest): (Vec<_>, Vec<_>) = joining
+            .into_iter()
+            .partition(|(sig, _)| placement(rules, sig).priority == priority);
+        joining = rest;
+
+        let priority_at = |i: usize| placement(rules, &merged[i].0).priority;
+        let end = (0..merged.len())
+            .rposition(|i| priority_at(i) >= priority)
+            .map_or(0, |i| i + 1);
+        let anchor = focused
+            .and_then(|f| merged.iter().position(|(sig, _)| sig == f))
+            .filter(|&i| priority_at(i) == priority);
+        let at = match (insertion, anchor) {
+            (Insertion::Start, _) => (0..merged.len())
+                .position(|i| priority_at(i) <= priority)
+                .unwrap_or(merged.len()),
+            (Insertion::AfterFocused, Some(anchor)) => anchor + 1,
+            (Insertion::BeforeFocused, Some(anchor)) => anchor,
+            _ => end,
+        };
+        merged.splice(at..at, block);
+    }
+
+    // Filling slots from either end outwards keeps earlier ones in place.
+    seated.sort_by_key(|&(slot, _)| if slot >= 0 { (0, slot) } else { (1, -slot) });
+    for (slot, entry) in seated {
+        let at = slot_index(slot, merged.len() + 1);
+        merged.insert(at, entry);
+    }
+
+    merged.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WindowRule;
+
+    fn sig(app: &str) -> WindowSignature {
+        (app.to_string(), String::new())
+    }
+
+    fn sigs(apps: &[&str]) -> Vec<WindowSignature> {
+        apps.iter().map(|app| sig(app)).collect()
+    }
+
+    fn apps(merged: Vec<WindowSignature>) -> Vec<String> {
+        merged.into_iter().map(|(app, _)| app).collect()
+    }
+
+    fn rule(app: &str, priority: i32, slot: Option<isize>) -> OrderRule {
+        OrderRule {
+            rule: WindowRule {
+                app: Some(app.to_string()),
+                title: None,
+            },
+            priority,
+            slot,
+        }
+    }
+
+    fn merge_apps(
+        order: &[&str],
+        live: &[&str],
+        rules: &[OrderRule],
+        insertion: Insertion,
+        focused: Option<&str>,
+    ) -> Vec<String> {
+        let focused = focused.map(sig);
+        apps(merge(
+            &sigs(order),
+            sigs(live),
+            Clone::clone,
+            rules,
+            insertion,
+            focused.as_ref(),
+        ))
+    }
+
+    #[test]
+    fn known_windows_keep_their_order() {
+        let merged = merge_apps(
+            &["C", "A", "B"],
+            &["A", "B", "C"],
+            &[],
+            Insertion::End,
+            None,
+        );
+        assert_eq!(merged, ["C", "A", "B"]);
+    }
+
+    #[test]
+    fn each_policy_places_new_windows() {
+        let cases = [
+            (Insertion::End, ["A", "B", "C", "X", "Y"]),
+            (Insertion::Start, ["X", "Y", "A", "B", "C"]),
+            (Insertion::AfterFocused, ["A", "B", "X", "Y", "C"]),
+            (Insertion::BeforeFocused, ["A", "X", "Y", "B", "C"]),
+        ];
+        for (insertion, expected) in cases {
+            let merged = merge_apps(
+                &["A", "B", "C"],
+                &["Y", "C", "X", "A", "B"],
+                &[],
+                insertion,
+                Some("B"),
+            );
+            assert_eq!(merged, expected, "{:?}", insertion);
+        }
+    }
+
+    #[test]
+    fn focus_policies_fall_back_to_the_end() {
+        for insertion in [Insertion::AfterFocused, Insertion::BeforeFocused] {
+            // No focused window, or one that is itself new.
+            for focused in [None, Some("X")] {
+                let merged = merge_apps(&["A", "B"], &["X", "A", "B"], &[], insertion, focused);
+                assert_eq!(merged, ["A", "B", "X"], "{:?} {:?}", insertion, focused);
+            }
+        }
+    }
+
+    #[test]
+    fn result_does_not_depend_on_live_order() {
+        let live = ["D", "A", "C", "B", "E"];
+        for insertion in [
+            Insertion::End,
+            Insertion::Start,
+            Insertion::AfterFocused,
+            Insertion::BeforeFocused,
+        ] {
+            let expected = merge_apps(&["A", "B"], &live, &[], insertion, Some("A"));
+            let mut shuffled = live;
+            for _ in 0..live.len() {
+                shuffled.rotate_left(1);
+                shuffled.swap(0, 3);
+                let merged = merge_apps(&["A", "B"], &shuffled, &[], insertion, Some("A"));
+                assert_eq!(merged, expected, "{:?} {:?}", insertion, shuffled);
+            }
+        }
+    }
+
+    #[test]
+    fn identical_signatures_are_all_kept() {
+        let merged = merge_apps(&["A"], &["A", "B", "A"], &[], Insertion::End, None);
+        assert_eq!(merged, ["A", "A", "B"]);
+    }
+
+    #[test]
+    fn priority_outranks_the_policy() {
+        let rules = [rule("Zed", 10, None), rule("Slack", -10, None)];
+        let merged = merge_apps(
+            &["A", "B"],
+            &["Slack", "A", "Zed", "B", "X"],
+            &rules,
+            Insertion::Start,
+            None,
+        );
+        assert_eq!(merged, ["Zed", "X", "A", "B", "Slack"]);
+    }
+
+    #[test]
+    fn slotted_windows_hold_their_slot() {
+        let rules = [rule("Zed", 0, Some(0)), rule("Slack", 0, Some(-1))];
+        let merged = merge_apps(&[], &["Slack", "A", "Zed"], &rules, Insertion::End, None);
+        assert_eq!(merged, ["Zed", "A", "Slack"]);
+
+        let merged = merge_apps(
+            &["Zed", "A", "Slack"],
+            &["B", "Slack", "A", "Zed"],
+            &rules,
+            Insertion::Start,
+            None,
+        );
+        assert_eq!(merged, ["Zed", "B", "A", "Slack"]);
+    }
+
+    #[test]
+    fn manual_moves_stick() {
+        let rules = [rule("Slack", 0, Some(-1))];
+        // Slack was promoted to master, out of its slot.
+        let merged = merge_apps(
+            &["Slack", "A"],
+            &["A", "B", "Slack"],
+            &rules,
+            Insertion::End,
+            None,
+        );
+        assert_eq!(merged, ["Slack", "A", "B"]);
+    }
+}
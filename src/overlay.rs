@@ -0,0 +1,195 @@
+use objc2::msg_send;
+use objc2::runtime::{AnyClass, AnyObject};
+use std::os::raw::c_void;
+
+use crate::border::{BorderGeometry, border_geometry, parse_color};
+use crate::config::{BorderConfig, Config};
+use crate::core_graphics::{CGColorCreateSRGB, CGColorRelease, CGRect, main_screen_rect};
+use crate::geometry::Rect;
+use crate::window::{
+    AXUIElementRef, Window, WindowObserver, element_rect, is_window_minimized, window_rect,
+};
+use crate::{is_tileable, load_state, signature, tiling_area};
+
+// NSApplicationActivationPolicyAccessory: no Dock icon or menu bar.
+const ACTIVATION_POLICY_ACCESSORY: isize = 1;
+// NSWindowStyleMaskBorderless
+const STYLE_MASK_BORDERLESS: usize = 0;
+// NSBackingStoreBuffered
+const BACKING_STORE_BUFFERED: usize = 2;
+// NSFloatingWindowLevel
+const FLOATING_WINDOW_LEVEL: isize = 3;
+// CanJoinAllSpaces | Stationary | IgnoresCycle
+const COLLECTION_BEHAVIOR: usize = 1 | 1 << 4 | 1 << 6;
+
+fn shared_application() -> *mut AnyObject {
+    let nsapplication = AnyClass::get(c"NSApplication").expect("NSApplication class not found");
+    unsafe { msg_send![nsapplication, sharedApplication] }
+}
+
+/// Sets up the process to own windows without showing up in the Dock.
+pub fn init_app() {
+    let app = shared_application();
+    unsafe {
+        let _: bool = msg_send![app, setActivationPolicy: ACTIVATION_POLICY_ACCESSORY];
+    }
+}
+
+/// Runs the AppKit event loop, which also drives the main run loop. Never
+/// returns.
+pub fn run_app() {
+    let app = shared_application();
+    unsafe {
+        let _: () = msg_send![app, run];
+    }
+}
+
+/// A click-through, borderless window that draws nothing but a rounded
+/// stroke, kept above normal windows on every Space.
+struct Overlay {
+    window: *mut AnyObject,
+    layer: *mut AnyObject,
+}
+
+impl Overlay {
+    pub fn new(config: &BorderConfig) -> Result<Self, String> {
+        let [red, green, blue, alpha] = parse_color(&config.color)
+            .ok_or_else(|| format!("Invalid border color: {}", config.color))?;
+        let window_class = AnyClass::get(c"NSWindow").ok_or("NSWindow class not found")?;
+        let color_class = AnyClass::get(c"NSColor").ok_or("NSColor class not found")?;
+
+        unsafe {
+            let window: *mut AnyObject = msg_send![window_class, alloc];
+            let window: *mut AnyObject = msg_send![
+                window,
+                initWithContentRect: CGRect::from(Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 1.0,
+                    height: 1.0,
+                }),
+                styleMask: STYLE_MASK_BORDERLESS,
+                backing: BACKING_STORE_BUFFERED,
+                defer: false
+            ];
+            if window.is_null() {
+                return Err("Could not create the border window".to_string());
+            }
+            let clear: *mut AnyObject = msg_send![color_class, clearColor];
+            let _: () = msg_send![window, setReleasedWhenClosed: false];
+            let _: () = msg_send![window, setOpaque: false];
+            let _: () = msg_send![window, setHasShadow: false];
+            let _: () = msg_send![window, setBackgroundColor: clear];
+            let _: () = msg_send![window, setIgnoresMouseEvents: true];
+            let _: () = msg_send![window, setLevel: FLOATING_WINDOW_LEVEL];
+            let _: () = msg_send![window, setCollectionBehavior: COLLECTION_BEHAVIOR];
+
+            let view: *mut AnyObject = msg_send![window, contentView];
+            let _: () = msg_send![view, setWantsLayer: true];
+            let layer: *mut AnyObject = msg_send![view, layer];
+            let color = CGColorCreateSRGB(red, green, blue, alpha);
+            let _: () = msg_send![layer, setBorderColor: color];
+            CGColorRelease(color);
+            let _: () = msg_send![layer, setBorderWidth: config.width];
+
+            Ok(Overlay { window, layer })
+        }
+    }
+
+    pub fn show(&self, geometry: BorderGeometry) {
+        // AppKit measures from the bottom left of the main display.
+        let main_display = main_screen_rect();
+        let frame = Rect {
+            y: main_display.height - geometry.frame.y - geometry.frame.height,
+            ..geometry.frame
+        };
+        unsafe {
+            let _: () = msg_send![self.layer, setCornerRadius: geometry.radius];
+            let _: () = msg_send![self.window, setFrame: CGRect::from(frame), display: true];
+            let _: () = msg_send![self.window, orderFrontRegardless];
+        }
+    }
+
+    pub fn hide(&self) {
+        unsafe {
+            let _: () = msg_send![self.window, orderOut: std::ptr::null::<AnyObject>()];
+        }
+    }
+}
+
+/// Where the border goes, shared with the AX observer callback.
+struct Placement {
+    overlay: Overlay,
+    width: f64,
+    radius: f64,
+    gap: f64,
+}
+
+impl Placement {
+    fn place(&self, window: Rect) {
+        self.overlay
+            .show(border_geometry(window, self.width, self.radius, self.gap));
+    }
+}
+
+extern "C" fn on_window_changed(
+    _observer: *const c_void,
+    element: AXUIElementRef,
+    _notification: *const c_void,
+    refcon: *mut c_void,
+) {
+    let placement = unsafe { &*(refcon as *const Placement) };
+    if let Some(rect) = element_rect(element) {
+        placement.place(rect);
+    }
+}
+
+/// Keeps the border overlay on the focused window while it is tiled,
+/// following it as it moves or resizes.
+pub struct FocusBorder {
+    // Declared first so it stops calling back before `placement` is freed.
+    observer: Option<WindowObserver>,
+    placement: Box<Placement>,
+    config: Config,
+}
+
+impl FocusBorder {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        Ok(FocusBorder {
+            observer: None,
+            placement: Box::new(Placement {
+                overlay: Overlay::new(&config.border)?,
+                width: config.border.width,
+                radius: config.border.radius,
+                gap: config.gap,
+            }),
+            config: config.clone(),
+        })
+    }
+
+    pub fn follow(&mut self, window: Option<Window>) {
+        self.observer = None;
+        let config = &self.config;
+        let Some(window) =
+            window.filter(|w| !config.ignores(&w.app_name, &w.title) && is_tiled(w, config))
+        else {
+            self.placement.overlay.hide();
+            return;
+        };
+        if let Some(rect) = window_rect(&window) {
+            self.placement.place(rect);
+        }
+        let refcon = &*self.placement as *const Placement as *mut c_void;
+        self.observer = WindowObserver::new(window, on_window_changed, refcon);
+    }
+}
+
+fn is_tiled(window: &Window, config: &Config) -> bool {
+    let state = load_state();
+    !state.floating.contains(&signature(window))
+        && is_tileable(
+            window_rect(window),
+            is_window_minimized(window),
+            tiling_area(config, &state),
+        )
+}
@@ -0,0 +1,405 @@
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::core_graphics::{Display, DisplayId, displays, main_screen_scale};
+use crate::geometry::Rect;
+use crate::layout::{Layout, gapped_layout_rects, group_tiles};
+use crate::window::{Window, is_window_minimized, window_rect};
+use crate::{State, WindowSignature, is_tileable, tiling_area};
+
+/// A live window as seen through the Accessibility API.
+pub struct ObservedWindow {
+    pub app: String,
+    pub title: String,
+    pub rect: Option<Rect>,
+    pub minimized: bool,
+}
+
+impl ObservedWindow {
+    fn observe(window: &Window) -> Self {
+        ObservedWindow {
+            app: window.app_name.clone(),
+            title: window.title.clone(),
+            rect: window_rect(window),
+            minimized: is_window_minimized(window),
+        }
+    }
+
+    fn signature(&self) -> WindowSignature {
+        (self.app.clone(), self.title.clone())
+    }
+}
+
+/// One entry of `vega query windows`.
+#[derive(Serialize, Debug)]
+pub struct WindowInfo {
+    /// `[app, title]`, the key vega uses to remember a window across runs.
+    pub signature: WindowSignature,
+    pub app: String,
+    pub title: String,
+    /// Top-left origin in global display coordinates; `null` if the window
+    /// did not report a position.
+    pub rect: Option<Rect>,
+    pub minimized: bool,
+    /// Visible but not managed by the tiler.
+    pub floating: bool,
+    /// Index into `vega query displays` of the display holding the window's
+    /// centre.
+    pub display: Option<usize>,
+    /// Position in the saved `window_order`; `null` for windows vega has not
+    /// tiled yet.
+    pub order: Option<usize>,
+}
+
+/// One entry of `vega query displays`.
+#[derive(Serialize, Debug)]
+pub struct DisplayInfo {
+    pub index: usize,
+    /// The system's ID for the display, valid until it is reconnected.
+    pub id: DisplayId,
+    /// The key for per-display config and state; empty if unknown.
+    pub uuid: String,
+    pub name: String,
+    pub rect: Rect,
+    /// Backing pixels per point.
+    pub scale: f64,
+    pub main: bool,
+    /// Number of windows whose centre lies on this display.
+    pub windows: usize,
+}
+
+/// Output of `vega query layout`: the tiles the current layout produces on
+/// the main display, master first. Stacked windows repeat their tile's rect.
+#[derive(Serialize, Debug)]
+pub struct LayoutInfo {
+    pub layout: Layout,
+    /// The part of the main display tiles go in.
+    pub display: Rect,
+    pub tiles: Vec<TileInfo>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TileInfo {
+    pub signature: WindowSignature,
+    pub rect: Rect,
+}
+
+pub fn describe_windows(
+    windows: &[ObservedWindow],
+    displays: &[Rect],
+    main_display: Rect,
+    window_order: &[WindowSignature],
+    floating: &[WindowSignature],
+) -> Vec<WindowInfo> {
+    windows
+        .iter()
+        .map(|w| {
+            let signature = w.signature();
+            let floating = !w.minimized
+                && (floating.contains(&signature)
+                    || !is_tileable(w.rect, w.minimized, main_display));
+            WindowInfo {
+                order: window_order.iter().position(|sig| *sig == signature),
+                signature,
+                app: w.app.clone(),
+                title: w.title.clone(),
+                rect: w.rect,
+                minimized: w.minimized,
+                floating,
+                display: w.rect.and_then(|rect| {
+                    let (cx, cy) = rect.center();
+                    displays.iter().position(|d| d.contains(cx, cy))
+                }),
+            }
+        })
+        .collect()
+}
+
+pub fn describe_displays(displays: &[Display], windows: &[WindowInfo]) -> Vec<DisplayInfo> {
+    displays
+        .iter()
+        .map(|display| DisplayInfo {
+            index: display.index,
+            id: display.id,
+            uuid: display.uuid.clone(),
+            name: display.name.clone(),
+            rect: display.bounds,
+            scale: display.scale,
+            main: display.main,
+            windows: windows
+                .iter()
+                .filter(|w| w.display == Some(display.index))
+                .count(),
+        })
+        .collect()
+}
+
+pub fn describe_layout(
+    layout: Layout,
+    main_display: Rect,
+    windows: &[ObservedWindow],
+    floating: &[WindowSignature],
+    stacks: &[Vec<WindowSignature>],
+    gap: f64,
+    scale: f64,
+) -> LayoutInfo {
+    let tiled: Vec<_> = windows
+        .iter()
+        .filter(|w| !floating.contains(&w.signature()))
+        .filter(|w| is_tileable(w.rect, w.minimized, main_display))
+        .map(ObservedWindow::signature)
+        .collect();
+    let groups = group_tiles(&tiled, stacks);
+    let tiled = &tiled;
+    let rects = gapped_layout_rects(layout, main_display, groups.len(), gap, scale);
+
+    LayoutInfo {
+        layout,
+        display: main_display,
+        tiles: groups
+            .iter()
+            .zip(rects)
+            .flat_map(|(group, rect)| {
+                group.iter().map(move |&i| TileInfo {
+                    signature: tiled[i].clone(),
+                    rect,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Prints the JSON for `vega query <target>`. `windows` must already be in
+/// tiling order.
+pub fn run(
+    target: Option<&str>,
+    config: &Config,
+    state: &State,
+    windows: &[Window],
+) -> Result<(), String> {
+    let observed: Vec<_> = windows.iter().map(ObservedWindow::observe).collect();
+    let main_display = tiling_area(config, state);
+
+    match target {
+        Some("windows") => {
+            let rects: Vec<_> = displays().iter().map(|d| d.bounds).collect();
+            print_json(&describe_windows(
+                &observed,
+                &rects,
+                main_display,
+                &state.window_order,
+                &state.floating,
+            ))
+        }
+        Some("displays") => {
+            let displays = displays();
+            let rects: Vec<_> = displays.iter().map(|d| d.bounds).collect();
+            let infos = describe_windows(
+                &observed,
+                &rects,
+                main_display,
+                &state.window_order,
+                &state.floating,
+            );
+            print_json(&describe_displays(&displays, &infos))
+        }
+        Some("layout") => print_json(&describe_layout(
+            state.current_layout,
+            main_display,
+            &observed,
+            &state.floating,
+            &state.stacks,
+            config.gap,
+            main_screen_scale(),
+        )),
+        Some("state") => print_json(state),
+        Some(other) => Err(format!(
+            "Unknown query: {} (expected windows, displays, layout or state)",
+            other
+        )),
+        None => Err("Usage: vega query <windows|displays|layout|state>".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed(app: &str, title: &str, rect: Option<Rect>, minimized: bool) -> ObservedWindow {
+        ObservedWindow {
+            app: app.to_string(),
+            title: title.to_string(),
+            rect,
+            minimized,
+        }
+    }
+
+    fn fixture() -> (Vec<ObservedWindow>, Vec<Rect>, Vec<WindowSignature>) {
+        let windows = vec![
+            observed(
+                "Zed",
+                "main.rs",
+                Some(Rect::new(0.0, 0.0, 800.0, 900.0)),
+                false,
+            ),
+            observed(
+                "Safari",
+                "Docs",
+                Some(Rect::new(2000.0, 100.0, 600.0, 400.0)),
+                false,
+            ),
+            observed(
+                "Mail",
+                "Inbox",
+                Some(Rect::new(100.0, 100.0, 400.0, 300.0)),
+                true,
+            ),
+        ];
+        let displays = vec![
+            Rect::new(0.0, 0.0, 1440.0, 900.0),
+            Rect::new(1440.0, 0.0, 1920.0, 1080.0),
+        ];
+        let order = vec![
+            ("Zed".to_string(), "main.rs".to_string()),
+            ("Mail".to_string(), "Inbox".to_string()),
+        ];
+        (windows, displays, order)
+    }
+
+    #[test]
+    fn windows_snapshot() {
+        let (windows, displays, order) = fixture();
+        let infos = describe_windows(&windows, &displays, displays[0], &order, &[]);
+
+        assert_eq!(
+            serde_json::to_string_pretty(&infos).unwrap(),
+            r#"[
+  {
+    "signature": [
+      "Zed",
+      "main.rs"
+    ],
+    "app": "Zed",
+    "title": "main.rs",
+    "rect": {
+      "x": 0.0,
+      "y": 0.0,
+      "width": 800.0,
+      "height": 900.0
+    },
+    "minimized": false,
+    "floating": false,
+    "display": 0,
+    "order": 0
+  },
+  {
+    "signature": [
+      "Safari",
+      "Docs"
+    ],
+    "app": "Safari",
+    "title": "Docs",
+    "rect": {
+      "x": 2000.0,
+      "y": 100.0,
+      "width": 600.0,
+      "height": 400.0
+    },
+    "minimized": false,
+    "floating": true,
+    "display": 1,
+    "order": null
+  },
+  {
+    "signature": [
+      "Mail",
+      "Inbox"
+    ],
+    "app": "Mail",
+    "title": "Inbox",
+    "rect": {
+      "x": 100.0,
+      "y": 100.0,
+      "width": 400.0,
+      "height": 300.0
+    },
+    "minimized": true,
+    "floating": false,
+    "display": 0,
+    "order": 1
+  }
+]"#
+        );
+    }
+
+    #[test]
+    fn displays_snapshot() {
+        let (windows, rects, order) = fixture();
+        let infos = describe_windows(&windows, &rects, rects[0], &order, &[]);
+        let displays = vec![
+            Display {
+                id: 1,
+                index: 0,
+                uuid: "37D8832A-2D66-02CA-B9F7-8F30A301B230".to_string(),
+                name: "Built-in Retina Display".to_string(),
+                scale: 2.0,
+                main: true,
+                bounds: rects[0],
+            },
+            Display {
+                id: 2,
+                index: 1,
+                uuid: "9A0B4C1D-5E6F-4A7B-8C9D-0E1F2A3B4C5D".to_string(),
+                name: "DELL U2720Q".to_string(),
+                scale: 1.0,
+                main: false,
+                bounds: rects[1],
+            },
+        ];
+
+        assert_eq!(
+            serde_json::to_string(&describe_displays(&displays, &infos)).unwrap(),
+            concat!(
+                r#"[{"index":0,"id":1,"uuid":"37D8832A-2D66-02CA-B9F7-8F30A301B230","name":"Built-in Retina Display","#,
+                r#""rect":{"x":0.0,"y":0.0,"width":1440.0,"height":900.0},"scale":2.0,"main":true,"windows":2},"#,
+                r#"{"index":1,"id":2,"uuid":"9A0B4C1D-5E6F-4A7B-8C9D-0E1F2A3B4C5D","name":"DELL U2720Q","#,
+                r#""rect":{"x":1440.0,"y":0.0,"width":1920.0,"height":1080.0},"scale":1.0,"main":false,"windows":1}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn layout_snapshot() {
+        let (mut windows, displays, _) = fixture();
+        windows.push(observed(
+            "Terminal",
+            "zsh",
+            Some(Rect::new(500.0, 500.0, 300.0, 200.0)),
+            false,
+        ));
+
+        assert_eq!(
+            serde_json::to_string(&describe_layout(
+                Layout::Vertical,
+                displays[0],
+                &windows,
+                &[],
+                &[],
+                0.0,
+                1.0
+            ))
+            .unwrap(),
+            concat!(
+                r#"{"layout":"Vertical","display":{"x":0.0,"y":0.0,"width":1440.0,"height":900.0},"tiles":["#,
+                r#"{"signature":["Zed","main.rs"],"rect":{"x":0.0,"y":0.0,"width":864.0,"height":900.0}},"#,
+                r#"{"signature":["Terminal","zsh"],"rect":{"x":864.0,"y":0.0,"width":576.0,"height":900.0}}]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::State;
+use crate::config::Config;
+use crate::core_graphics::{Display, displays};
+use crate::geometry::Insets;
+
+const USAGE: &str = "Usage: vega reserve <display> <top|bottom|left|right>=<points>... | clear";
+
+/// The space kept clear on `display`: whatever `vega reserve` last set for
+/// it, or else what the config gives.
+pub fn reserve_for(config: &Config, state: &State, display: &Display) -> Insets {
+    state
+        .reserves
+        .get(&display.uuid)
+        .copied()
+        .unwrap_or_else(|| config.reserve_for(display))
+}
+
+/// `vega reserve <display> top=32`: sets the given edges of a display's
+/// reserve, keeping the rest, so a bar can claim space as it starts.
+/// `clear` drops back to the configured reserve. The display is picked by
+/// index, name or UUID.
+pub fn run(args: &[String], config: &Config, state: &mut State) -> Result<(), String> {
+    let (Some(selector), edges) = (args.first(), args.get(1..).unwrap_or_default()) else {
+        return Err(USAGE.to_string());
+    };
+    if edges.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let display = displays()
+        .into_iter()
+        .find(|d| d.matches(selector))
+        .ok_or_else(|| format!("No display matches {}", selector))?;
+    let label = if display.name.is_empty() {
+        display.index.to_string()
+    } else {
+        display.name.clone()
+    };
+    // Reserves are kept by UUID, and one set under an empty UUID would
+    // apply to every display without one.
+    if display.uuid.is_empty() {
+        return Err(format!(
+            "Display {} has no stable ID to keep a reserve under",
+            label
+        ));
+    }
+
+    match edit(reserve_for(config, state, &display), edges)? {
+        Some(reserve) => {
+            println!(
+                "Reserving top {}, bottom {}, left {}, right {} on {}",
+                reserve.top, reserve.bottom, reserve.left, reserve.right, label
+            );
+            state.reserves.insert(display.uuid, reserve);
+        }
+        None => {
+            state.reserves.remove(&display.uuid);
+            println!("Cleared the reserve set for {}", label);
+        }
+    }
+    Ok(())
+}
+
+/// `reserve` with `edges` applied, or `None` for `clear`.
+fn edit(mut reserve: Insets, edges: &[String]) -> Result<Option<Insets>, String> {
+    if edges == ["clear"] {
+        return Ok(None);
+    }
+    for edge in edges {
+        let invalid = || format!("Invalid edge: {}\n{}", edge, USAGE);
+        let (name, value) = edge.split_once('=').ok_or_else(invalid)?;
+        let value: f64 = value
+            .parse()
+            .ok()
+            .filter(|&v: &f64| v.is_finite() && v >= 0.0)
+            .ok_or_else(invalid)?;
+        match name {
+            "top" => reserve.top = value,
+            "bottom" => reserve.bottom = value,
+            "left" => reserve.left = value,
+            "right" => reserve.right = value,
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(Some(reserve))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::DisplayConfig;
+    use crate::geometry::Rect;
+
+    const BOUNDS: Rect = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+
+    fn top(top: f64) -> Insets {
+        Insets {
+            top,
+            ..Insets::default()
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn displays_match_by_index_name_or_uuid() {
+        let dell = Display::new(1, "37D8832A-2D66", "DELL U2720Q", BOUNDS);
+        assert!(dell.matches("1"));
+        assert!(dell.matches("DELL U2720Q"));
+        assert!(dell.matches("37d8832a-2d66"));
+        assert!(!dell.matches("0"));
+        assert!(!dell.matches("dell u2720q"));
+
+        // Without a name or UUID, the empty selector must not match.
+        let unknown = Display::new(2, "", "", BOUNDS);
+        assert!(!unknown.matches(""));
+        assert!(unknown.matches("2"));
+    }
+
+    #[test]
+    fn config_reserve_prefers_uuid_then_name_then_index() {
+        let dell = Display::new(1, "37D8832A-2D66", "DELL U2720Q", BOUNDS);
+        let entry = |t| DisplayConfig {
+            reserve: Some(top(t)),
+        };
+        let mut config = Config {
+            reserve: top(1.0),
+            ..Config::default()
+        };
+        assert_eq!(config.reserve_for(&dell), top(1.0));
+
+        config.displays.insert("1".to_string(), entry(2.0));
+        assert_eq!(config.reserve_for(&dell), top(2.0));
+        config
+            .displays
+            .insert("DELL U2720Q".to_string(), entry(3.0));
+        assert_eq!(config.reserve_for(&dell), top(3.0));
+        config
+            .displays
+            .insert("37d8832a-2d66".to_string(), entry(4.0));
+        assert_eq!(config.reserve_for(&dell), top(4.0));
+
+        // An entry without a reserve falls back to the top-level one.
+        config
+            .displays
+            .insert("2".to_string(), DisplayConfig::default());
+        assert_eq!(
+            config.reserve_for(&Display::new(2, "", "", BOUNDS)),
+            top(1.0)
+        );
+    }
+
+    #[test]
+    fn set_reserves_replace_the_configured_one() {
+        let dell = Display::new(1, "37D8832A-2D66", "DELL U2720Q", BOUNDS);
+        let config = Config {
+            reserve: top(1.0),
+            ..Config::default()
+        };
+        let mut state = crate::State {
+            reserves: HashMap::from([(dell.uuid.clone(), top(32.0))]),
+            ..crate::State::default()
+        };
+        assert_eq!(reserve_for(&config, &state, &dell), top(32.0));
+        state.reserves.clear();
+        assert_eq!(reserve_for(&config, &state, &dell), top(1.0));
+    }
+
+    #[test]
+    fn edges_are_set_and_the_rest_kept() {
+        let current = Insets {
+            top: 1.0,
+            bottom: 2.0,
+            left: 3.0,
+            right: 4.0,
+        };
+        assert_eq!(
+            edit(current, &args(&["top=32"])),
+            Ok(Some(Insets {
+                top: 32.0,
+                ..current
+            }))
+        );
+        assert_eq!(
+            edit(current, &args(&["left=0", "right=8.5"])),
+            Ok(Some(Insets {
+                left: 0.0,
+                right: 8.5,
+                ..current
+            }))
+        );
+        assert_eq!(edit(current, &args(&["clear"])), Ok(None));
+    }
+
+    #[test]
+    fn malformed_edges_are_rejected() {
+        for edge in [
+            "top", "top=", "top=-1", "top=abc", "top=inf", "top=NaN", "middle=4", "clear=1",
+        ] {
+            assert!(edit(Insets::default(), &args(&[edge])).is_err(), "{}", edge);
+        }
+        // `clear` only stands alone.
+        assert!(edit(Insets::default(), &args(&["clear", "top=4"])).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use std::os::raw::c_void;
+use std::ptr;
+use std::time::Duration;
+
+type CFRunLoopRef = *const c_void;
+type CFRunLoopTimerRef = *const c_void;
+pub type CFRunLoopSourceRef = *const c_void;
+type CFStringRef = *const c_void;
+type CFIndex = isize;
+type CFAbsoluteTime = f64;
+
+#[repr(C)]
+struct CFRunLoopTimerContext {
+    version: CFIndex,
+    info: *mut c_void,
+    retain: *const c_void,
+    release: *const c_void,
+    copy_description: *const c_void,
+}
+
+type CFRunLoopTimerCallBack = extern "C" fn(timer: CFRunLoopTimerRef, info: *mut c_void);
+
+unsafe extern "C" {
+    fn CFRunLoopGetMain() -> CFRunLoopRef;
+    fn CFRunLoopRun();
+    fn CFRunLoopAddSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+    fn CFRunLoopRemoveSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+    fn CFRunLoopAddTimer(rl: CFRunLoopRef, timer: CFRunLoopTimerRef, mode: CFStringRef);
+    fn CFRunLoopTimerCreate(
+        allocator: *const c_void,
+        fire_date: CFAbsoluteTime,
+        interval: f64,
+        flags: usize,
+        order: CFIndex,
+        callout: CFRunLoopTimerCallBack,
+        context: *mut CFRunLoopTimerContext,
+    ) -> CFRunLoopTimerRef;
+    fn CFAbsoluteTimeGetCurrent() -> CFAbsoluteTime;
+
+    static kCFRunLoopCommonModes: CFStringRef;
+}
+
+extern "C" fn fire(_timer: CFRunLoopTimerRef, info: *mut c_void) {
+    let callback = unsafe { &mut *(info as *mut Box<dyn FnMut()>) };
+    callback();
+}
+
+/// Calls `callback` on the main thread every `interval` for as long as the
+/// main run loop runs.
+pub fn every(interval: Duration, callback: impl FnMut() + 'static) {
+    let callback: Box<Box<dyn FnMut()>> = Box::new(Box::new(callback));
+    let mut context = CFRunLoopTimerContext {
+        version: 0,
+        // Leaked on purpose: the timer lives as long as the process.
+        info: Box::into_raw(callback) as *mut c_void,
+        retain: ptr::null(),
+        release: ptr::null(),
+        copy_description: ptr::null(),
+    };
+    unsafe {
+        let secs = interval.as_secs_f64();
+        let timer = CFRunLoopTimerCreate(
+            ptr::null(),
+            CFAbsoluteTimeGetCurrent() + secs,
+            secs,
+            0,
+            0,
+            fire,
+            &mut context,
+        );
+        CFRunLoopAddTimer(CFRunLoopGetMain(), timer, kCFRunLoopCommonModes);
+    }
+}
+
+pub fn add_source(source: CFRunLoopSourceRef) {
+    unsafe { CFRunLoopAddSource(CFRunLoopGetMain(), source, kCFRunLoopCommonModes) };
+}
+
+pub fn remove_source(source: CFRunLoopSourceRef) {
+    unsafe { CFRunLoopRemoveSource(CFRunLoopGetMain(), source, kCFRunLoopCommonModes) };
+}
+
+/// Runs the main run loop. Only returns if it is stopped.
+pub fn run() {
+    unsafe { CFRunLoopRun() };
+}
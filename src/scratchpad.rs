@@ -0,0 +1,223 @@
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, ScratchpadConfig};
+use crate::core_graphics::{displays, main_screen_rect};
+use crate::focus::focus;
+use crate::geometry::Rect;
+use crate::hooks::Hooks;
+use crate::window::{
+    Window, collect_windows, focused_window_index, is_window_minimized, move_and_resize_window,
+    set_window_minimized, window_rect,
+};
+use crate::{State, WindowSignature, retile_windows, signature};
+
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(5);
+const LAUNCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// `vega scratchpad toggle <name>`: shows the scratchpad window floating in
+/// the middle of the active display, or minimizes it if it is showing.
+pub fn toggle(
+    name: &str,
+    config: &Config,
+    state: &mut State,
+    windows: &[Window],
+    hooks: &mut Hooks,
+) -> Result<(), String> {
+    let Some(pad) = config.scratchpads.get(name) else {
+        let mut names: Vec<_> = config.scratchpads.keys().map(String::as_str).collect();
+        names.sort();
+        return Err(format!(
+            "Unknown scratchpad: {} (configured: {})",
+            name,
+            names.join(", ")
+        ));
+    };
+
+    let found = find_window(name, pad, state, windows, signature);
+    let (window, launched) = match found {
+        Some(window) => (window.clone(), false),
+        None if pad.launch => (launch(pad)?, true),
+        None => return Err(format!("No window matches scratchpad {}", name)),
+    };
+
+    let newly_floating = adopt(state, name, signature(&window));
+
+    if launched || is_window_minimized(&window) {
+        println!("Showing scratchpad '{}'", name);
+        set_window_minimized(&window, false);
+        let display = active_display(windows);
+        move_and_resize_window(&window, display.centered(pad.width, pad.height));
+        focus(config, &window);
+    } else {
+        println!("Hiding scratchpad '{}'", name);
+        set_window_minimized(&window, true);
+    }
+
+    if newly_floating {
+        // The window may have been tiled before; close the gap it leaves.
+        retile_windows(config, state, windows, hooks);
+    }
+    Ok(())
+}
+
+/// The window last used for this scratchpad, or else the first one matching
+/// its rule.
+fn find_window<'a, T>(
+    name: &str,
+    pad: &ScratchpadConfig,
+    state: &State,
+    windows: &'a [T],
+    signature: impl Fn(&T) -> WindowSignature,
+) -> Option<&'a T> {
+    state
+        .scratchpads
+        .get(name)
+        .and_then(|sig| windows.iter().find(|w| signature(w) == *sig))
+        .or_else(|| {
+            windows.iter().find(|w| {
+                let (app, title) = signature(w);
+                pad.rule.matches(&app, &title)
+            })
+        })
+}
+
+/// Records `sig` as the scratchpad's window and floats it. A window found
+/// by its rule after its title changed replaces the stale signature, which
+/// would otherwise float nothing. Returns whether tiling needs redoing.
+fn adopt(state: &mut State, name: &str, sig: WindowSignature) -> bool {
+    let stale = state
+        .scratchpads
+        .insert(name.to_string(), sig.clone())
+        .filter(|old| *old != sig);
+    if let Some(old) = stale {
+        state.floating.retain(|s| *s != old);
+    }
+    if state.floating.contains(&sig) {
+        return false;
+    }
+    state.floating.push(sig);
+    true
+}
+
+fn launch(pad: &ScratchpadConfig) -> Result<Window, String> {
+    let app = pad
+        .rule
+        .app
+        .as_deref()
+        .ok_or("Scratchpads with launch enabled need an app")?;
+    println!("Launching {}", app);
+    let status = Command::new("open")
+        .arg("-a")
+        .arg(app)
+        .status()
+        .map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("Could not launch {}", app));
+    }
+
+    let started = Instant::now();
+    while started.elapsed() < LAUNCH_TIMEOUT {
+        if let Some(window) = collect_windows()
+            .into_iter()
+            .find(|w| pad.rule.matches(&w.app_name, &w.title))
+        {
+            return Ok(window);
+        }
+        thread::sleep(LAUNCH_POLL_INTERVAL);
+    }
+    Err(format!("{} did not open a matching window in time", app))
+}
+
+/// The display holding the focused window, falling back to the main one.
+fn active_display(windows: &[Window]) -> Rect {
+    let focused = focused_window_index(windows).and_then(|i| window_rect(&windows[i]));
+    let bounds: Vec<Rect> = displays().into_iter().map(|d| d.bounds).collect();
+    display_holding(focused, &bounds, main_screen_rect())
+}
+
+/// The display holding the center of `focused`, or else `main`.
+fn display_holding(focused: Option<Rect>, displays: &[Rect], main: Rect) -> Rect {
+    focused
+        .map(|rect| rect.center())
+        .and_then(|(x, y)| displays.iter().copied().find(|d| d.contains(x, y)))
+        .unwrap_or(main)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WindowRule;
+
+    fn sig(app: &str, title: &str) -> WindowSignature {
+        (app.to_string(), title.to_string())
+    }
+
+    fn terminal() -> ScratchpadConfig {
+        ScratchpadConfig {
+            rule: WindowRule {
+                app: Some("Terminal".to_string()),
+                title: None,
+            },
+            width: 800.0,
+            height: 600.0,
+            launch: false,
+        }
+    }
+
+    #[test]
+    fn the_last_used_window_is_found_first() {
+        let mut state = State::default();
+        state
+            .scratchpads
+            .insert("term".to_string(), sig("Terminal", "htop"));
+        let windows = [sig("Terminal", "zsh"), sig("Terminal", "htop")];
+        let found = find_window("term", &terminal(), &state, &windows, Clone::clone);
+        assert_eq!(found, Some(&windows[1]));
+    }
+
+    #[test]
+    fn a_stale_window_falls_back_to_the_rule() {
+        let mut state = State::default();
+        state
+            .scratchpads
+            .insert("term".to_string(), sig("Terminal", "vim"));
+        let windows = [sig("Zed", "main.rs"), sig("Terminal", "zsh")];
+        let found = find_window("term", &terminal(), &state, &windows, Clone::clone);
+        assert_eq!(found, Some(&windows[1]));
+
+        let windows = [sig("Zed", "main.rs")];
+        let found = find_window("term", &terminal(), &state, &windows, Clone::clone);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn a_renamed_window_replaces_its_stale_signature() {
+        let mut state = State::default();
+        assert!(adopt(&mut state, "term", sig("Terminal", "vim")));
+        assert!(!adopt(&mut state, "term", sig("Terminal", "vim")));
+
+        // The title changed, so the window came back by its rule.
+        assert!(adopt(&mut state, "term", sig("Terminal", "zsh")));
+        assert_eq!(state.scratchpads["term"], sig("Terminal", "zsh"));
+        assert_eq!(state.floating, vec![sig("Terminal", "zsh")]);
+    }
+
+    #[test]
+    fn scratchpads_show_on_the_focused_display() {
+        let main = Rect::new(0.0, 0.0, 1440.0, 900.0);
+        let side = Rect::new(1440.0, 0.0, 1920.0, 1080.0);
+        let displays = [main, side];
+
+        let focused = Rect::new(1500.0, 100.0, 800.0, 600.0);
+        assert_eq!(display_holding(Some(focused), &displays, main), side);
+        // Centered on the main display, though it reaches onto the side one.
+        let straddling = Rect::new(1000.0, 100.0, 800.0, 600.0);
+        assert_eq!(display_holding(Some(straddling), &displays, main), main);
+        // Nothing focused, or focused off every display.
+        assert_eq!(display_holding(None, &displays, side), side);
+        let lost = Rect::new(-5000.0, 0.0, 100.0, 100.0);
+        assert_eq!(display_holding(Some(lost), &displays, main), main);
+    }
+}
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::mem;
+use std::path::PathBuf;
+
+use crate::config::{Config, get_config_file_path};
+use crate::core_graphics::displays;
+use crate::events::Event;
+use crate::hooks::Hooks;
+use crate::layout::Layout;
+use crate::window::{Window, is_window_minimized, move_and_resize_window, window_rect};
+use crate::{State, WindowSignature, announce, order_windows, retile_windows, signature};
+
+/// A saved arrangement, stored as pretty JSON under
+/// `~/.config/vega/sessions/<name>.json` so it can be edited by hand.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Session {
+    pub layout: Layout,
+    /// Windows in tiling order, master first.
+    pub windows: Vec<SessionWindow>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionWindow {
+    pub app: String,
+    pub title: String,
+    /// Index of the display the window was on.
+    #[serde(default)]
+    pub display: Option<usize>,
+    /// UUID of that display, which is preferred over the index when it is
+    /// still connected.
+    #[serde(default)]
+    pub display_uuid: Option<String>,
+    #[serde(default)]
+    pub floating: bool,
+}
+
+fn get_session_file_path(name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid session name: {}", name));
+    }
+    let mut path = get_config_file_path();
+    path.pop();
+    path.push("sessions");
+    path.push(format!("{}.json", name));
+    Ok(path)
+}
+
+pub fn save(name: &str, state: &State, windows: &[Window]) -> Result<(), String> {
+    let displays = displays();
+    let session = Session {
+        layout: state.current_layout,
+        windows: windows
+            .iter()
+            .filter(|w| !is_window_minimized(w))
+            .map(|w| {
+                let display = window_rect(w).and_then(|rect| {
+                    let (cx, cy) = rect.center();
+                    displays.iter().find(|d| d.bounds.contains(cx, cy))
+                });
+                SessionWindow {
+                    app: w.app_name.clone(),
+                    title: w.title.clone(),
+                    display: display.map(|d| d.index),
+                    display_uuid: display
+                        .map(|d| d.uuid.clone())
+                        .filter(|uuid| !uuid.is_empty()),
+                    floating: state.floating.contains(&signature(w)),
+                }
+            })
+            .collect(),
+    };
+
+    let path = get_session_file_path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(&session).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string())?;
+    println!(
+        "Saved {} windows to {}",
+        session.windows.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Reapplies a saved session to the live windows it can match, leaving
+/// `windows` in their new order.
+pub fn restore(
+    name: &str,
+    config: &Config,
+    state: &mut State,
+    windows: &mut Vec<Window>,
+    hooks: &mut Hooks,
+) -> Result<(), String> {
+    let path = get_session_file_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read session {}: {}", path.display(), e))?;
+    let session: Session = serde_json::from_str(&content)
+        .map_err(|e| format!("Could not parse session {}: {}", path.display(), e))?;
+
+    let live: Vec<WindowSignature> = windows.iter().map(signature).collect();
+    let matches = match_windows(&session.windows, &live);
+    let displays = displays();
+
+    let mut order = Vec::new();
+    for (saved, matched) in session.windows.iter().zip(&matches) {
+        let Some(index) = *matched else {
+            continue;
+        };
+        let sig = live[index].clone();
+        let window = &windows[index];
+
+        state.floating.retain(|s| *s != sig);
+        if saved.floating {
+            state.floating.push(sig.clone());
+        }
+
+        // Carry the window over to its saved display; tiling then places it.
+        let display = saved
+            .display_uuid
+            .as_ref()
+            .and_then(|uuid| displays.iter().find(|d| d.uuid == *uuid))
+            .or_else(|| saved.display.and_then(|i| displays.get(i)))
+            .map(|d| d.bounds);
+        if let (Some(display), Some(rect)) = (display, window_rect(window)) {
+            let (cx, cy) = rect.center();
+            if !display.contains(cx, cy) {
+                move_and_resize_window(window, display.centered(rect.width, rect.height));
+            }
+        }
+        order.push(sig);
+    }
+    // Unmatched windows keep their current relative order after the session's.
+    let rest: Vec<_> = live
+        .iter()
+        .filter(|sig| !order.contains(sig))
+        .cloned()
+        .collect();
+    order.extend(rest);
+
+    println!(
+        "Restoring session '{}': matched {} of {} windows",
+        name,
+        matches.iter().flatten().count(),
+        session.windows.len()
+    );
+
+    state.current_layout = session.layout;
+    *windows = order_windows(config, &order, mem::take(windows), None);
+    retile_windows(config, state, windows, hooks);
+    announce(
+        hooks,
+        Event::LayoutChanged {
+            layout: state.current_layout,
+        },
+    );
+    Ok(())
+}
+
+/// Pairs each saved window with a distinct live window. Exact signatures win
+/// first; the rest go to the same app's window with the most similar title,
+/// so titles that changed since saving (a new tab, another file) still match.
+/// There is no minimum similarity: a browser's title can change entirely
+/// with the page, and the app's remaining window is still the best guess.
+fn match_windows(saved: &[SessionWindow], live: &[WindowSignature]) -> Vec<Option<usize>> {
+    let mut taken = vec![false; live.len()];
+    let mut matches: Vec<Option<usize>> = saved
+        .iter()
+        .map(|s| {
+            let index = live
+                .iter()
+                .enumerate()
+                .position(|(i, (app, title))| !taken[i] && *app == s.app && *title == s.title)?;
+            taken[index] = true;
+            Some(index)
+        })
+        .collect();
+
+    for (s, matched) in saved.iter().zip(matches.iter_mut()) {
+        if matched.is_some() {
+            continue;
+        }
+        let best = live
+            .iter()
+            .enumerate()
+            .filter(|(i, (app, _))| !taken[*i] && *app == s.app)
+            .max_by(|(i, (_, a)), (j, (_, b))| {
+                title_similarity(&s.title, a)
+                    .total_cmp(&title_similarity(&s.title, b))
+                    // Prefer the earlier window on ties.
+                    .then(j.cmp(i))
+            })
+            .map(|(i, _)| i);
+        if let Some(index) = best {
+            taken[index] = true;
+            *matched = Some(index);
+        }
+    }
+    matches
+}
+
+/// Share of words the two titles have in common, from 0.0 to 1.0.
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric() && c != '.' && c != '_')
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let common = a.iter().filter(|w| b.contains(w)).count();
+    common as f64 / a.len().max(b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(app: &str, title: &str) -> SessionWindow {
+        SessionWindow {
+            app: app.to_string(),
+            title: title.to_string(),
+            display: None,
+            display_uuid: None,
+            floating: false,
+        }
+    }
+
+    fn live(windows: &[(&str, &str)]) -> Vec<WindowSignature> {
+        windows
+            .iter()
+            .map(|(app, title)| (app.to_string(), title.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn exact_matches_come_first() {
+        // The first saved window would take "main.rs" by similarity if the
+        // second did not claim it exactly.
+        let saved = [saved("Zed", "main.rs — scratch"), saved("Zed", "main.rs")];
+        let live = live(&[("Zed", "lib.rs — vega"), ("Zed", "main.rs")]);
+        assert_eq!(match_windows(&saved, &live), [Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn changed_titles_go_to_the_most_similar_window() {
+        let saved = [
+            saved("Zed", "layout.rs — vega"),
+            saved("Zed", "notes.md — blog"),
+        ];
+        let live = live(&[
+            ("Zed", "drafts.md — blog"),
+            ("Zed", "main.rs — vega"),
+            ("Safari", "layout.rs — vega"),
+        ]);
+        assert_eq!(match_windows(&saved, &live), [Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn dissimilar_windows_of_the_same_app_still_match() {
+        let saved = [saved("Safari", "Rust Blog"), saved("Mail", "Inbox")];
+        let live = live(&[("Safari", "GitHub"), ("Zed", "Inbox")]);
+        assert_eq!(title_similarity("Rust Blog", "GitHub"), 0.0);
+        assert_eq!(match_windows(&saved, &live), [Some(0), None]);
+    }
+
+    #[test]
+    fn each_live_window_is_matched_once() {
+        let saved = [
+            saved("Terminal", "zsh"),
+            saved("Terminal", "zsh"),
+            saved("Terminal", "zsh"),
+        ];
+        let live = live(&[("Terminal", "zsh"), ("Terminal", "htop")]);
+        assert_eq!(match_windows(&saved, &live), [Some(0), Some(1), None]);
+    }
+
+    #[test]
+    fn similarity_counts_shared_words() {
+        assert_eq!(title_similarity("main.rs — vega", "main.rs — vega"), 1.0);
+        assert_eq!(title_similarity("main.rs — vega", "MAIN.RS"), 0.5);
+        assert_eq!(title_similarity("", " — "), 1.0);
+        assert_eq!(title_similarity("inbox", ""), 0.0);
+    }
+}
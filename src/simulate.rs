@@ -0,0 +1,326 @@
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::geometry::Rect;
+use crate::layout::{Layout, tile_windows};
+
+const USAGE: &str = "Usage: vega simulate [--windows N] [--display WxH[+X+Y]]... \
+                     [--layout <name>] [--gap N] [--scale N] [--format table|json|ascii]";
+/// Display used when none is given: a common laptop size.
+const DEFAULT_DISPLAY: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 1440.0,
+    height: 900.0,
+};
+/// Width of the ASCII diagram in characters.
+const DIAGRAM_COLUMNS: usize = 80;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Table,
+    Json,
+    Ascii,
+}
+
+/// One simulated window and where tiling put it.
+#[derive(Serialize, Debug)]
+struct Placement {
+    display: usize,
+    window: usize,
+    rect: Rect,
+}
+
+/// Parses `WxH`, optionally followed by a signed origin such as `+1440+0`
+/// or `-1920+0`.
+fn parse_display(spec: &str) -> Result<Rect, String> {
+    let invalid = || format!("Invalid display: {} (expected WxH[+X+Y])", spec);
+    let (width, rest) = spec.split_once('x').ok_or_else(invalid)?;
+    let origin_at = rest.find(['+', '-']).unwrap_or(rest.len());
+    let (height, origin) = rest.split_at(origin_at);
+
+    let (x, y) = if origin.is_empty() {
+        (0.0, 0.0)
+    } else {
+        let y_at = origin[1..].find(['+', '-']).ok_or_else(invalid)? + 1;
+        let (x, y) = origin.split_at(y_at);
+        (
+            x.parse().map_err(|_| invalid())?,
+            y.parse().map_err(|_| invalid())?,
+        )
+    };
+    let width: f64 = width.parse().map_err(|_| invalid())?;
+    let height: f64 = height.parse().map_err(|_| invalid())?;
+    if width <= 0.0 || height <= 0.0 || [x, y, width, height].iter().any(|v| !v.is_finite()) {
+        return Err(invalid());
+    }
+    Ok(Rect {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// A gap in points: finite and not negative.
+fn parse_gap(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&gap: &f64| gap.is_finite() && gap >= 0.0)
+        .ok_or_else(|| format!("Invalid gap: {}", value))
+}
+
+/// Pixels per point: finite and positive.
+fn parse_scale(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&scale: &f64| scale.is_finite() && scale > 0.0)
+        .ok_or_else(|| format!("Invalid scale: {}", value))
+}
+
+/// Tiles `count` stand-in windows on each display through the same
+/// [`tile_windows`] call real tiling makes, recording where each lands.
+fn simulate(
+    layout: Layout,
+    displays: &[Rect],
+    count: usize,
+    gap: f64,
+    scale: f64,
+) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    for (display, &rect) in displays.iter().enumerate() {
+        let tiles: Vec<Vec<usize>> = (0..count).map(|window| vec![window]).collect();
+        tile_windows(layout, rect, gap, scale, &tiles, |&window, rect| {
+            placements.push(Placement {
+                display,
+                window,
+                rect,
+            })
+        });
+    }
+    placements
+}
+
+fn print_table(placements: &[Placement]) {
+    println!(
+        "{:<8} {:<7} {:>10} {:>10} {:>10} {:>10}",
+        "display", "window", "x", "y", "width", "height"
+    );
+    for p in placements {
+        println!(
+            "{:<8} {:<7} {:>10} {:>10} {:>10} {:>10}",
+            p.display, p.window, p.rect.x, p.rect.y, p.rect.width, p.rect.height
+        );
+    }
+}
+
+/// Draws the displays as `.` and each window as a labelled box, scaled to
+/// fit [`DIAGRAM_COLUMNS`]. Earlier windows are drawn on top.
+fn print_diagram(displays: &[Rect], placements: &[Placement]) {
+    let left = displays.iter().map(|d| d.x).fold(f64::INFINITY, f64::min);
+    let top = displays.iter().map(|d| d.y).fold(f64::INFINITY, f64::min);
+    let right = displays
+        .iter()
+        .map(|d| d.x + d.width)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let bottom = displays
+        .iter()
+        .map(|d| d.y + d.height)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let scale = DIAGRAM_COLUMNS as f64 / (right - left);
+    // Character cells are roughly twice as tall as they are wide.
+    let rows = (((bottom - top) * scale / 2.0).round() as usize).max(1);
+    let mut grid = vec![vec![' '; DIAGRAM_COLUMNS]; rows];
+
+    // Cell bounds of `rect`, inclusive, or `None` if it has no area.
+    let cells = |rect: &Rect| {
+        let column = |x: f64| ((x - left) * scale).clamp(0.0, DIAGRAM_COLUMNS as f64);
+        let row = |y: f64| ((y - top) * scale / 2.0).clamp(0.0, rows as f64);
+        let (c0, c1) = (column(rect.x).floor(), column(rect.x + rect.width).ceil());
+        let (r0, r1) = (row(rect.y).floor(), row(rect.y + rect.height).ceil());
+        (c1 > c0 && r1 > r0).then(|| (c0 as usize, c1 as usize - 1, r0 as usize, r1 as usize - 1))
+    };
+
+    for display in displays {
+        if let Some((c0, c1, r0, r1)) = cells(display) {
+            for row in &mut grid[r0..=r1] {
+                row[c0..=c1].fill('.');
+            }
+        }
+    }
+    for p in placements.iter().rev() {
+        let Some((c0, c1, r0, r1)) = cells(&p.rect) else {
+            continue;
+        };
+        for (r, row) in grid.iter_mut().enumerate().take(r1 + 1).skip(r0) {
+            for (c, cell) in row.iter_mut().enumerate().take(c1 + 1).skip(c0) {
+                *cell = match (r == r0 || r == r1, c == c0 || c == c1) {
+                    (true, true) => '+',
+                    (true, false) => '-',
+                    (false, true) => '|',
+                    (false, false) => ' ',
+                };
+            }
+        }
+        let label = if displays.len() > 1 {
+            format!("{}:{}", p.display, p.window)
+        } else {
+            p.window.to_string()
+        };
+        if r1 > r0 + 1 && c1 > c0 + label.len() {
+            for (i, ch) in label.chars().enumerate() {
+                grid[r0 + 1][c0 + 1 + i] = ch;
+            }
+        }
+    }
+
+    for row in grid {
+        println!("{}", row.into_iter().collect::<String>().trim_end());
+    }
+}
+
+/// `vega simulate`: tiles stand-in windows on made-up displays and prints
+/// where they would go, without touching any real window. Each display is
+/// tiled on its own, as if it were the main display.
+pub fn run(args: &[String], config: &Config) -> Result<(), String> {
+    let mut count = 3;
+    let mut displays = Vec::new();
+    let mut layout = Layout::Vertical;
+    let mut gap = config.gap;
+    let mut scale = 1.0;
+    let mut format = Format::Table;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}\n{}", flag, USAGE))?;
+        match flag.as_str() {
+            "--windows" => {
+                count = value
+                    .parse()
+                    .map_err(|_| format!("Invalid window count: {}", value))?
+            }
+            "--display" => displays.push(parse_display(value)?),
+            "--layout" => layout = value.parse()?,
+            "--gap" => gap = parse_gap(value)?,
+            "--scale" => scale = parse_scale(value)?,
+            "--format" => {
+                format = match value.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    "ascii" => Format::Ascii,
+                    _ => {
+                        return Err(format!(
+                            "Unknown format: {} (expected table, json or ascii)",
+                            value
+                        ));
+                    }
+                }
+            }
+            _ => return Err(format!("Unknown option: {}\n{}", flag, USAGE)),
+        }
+    }
+    if displays.is_empty() {
+        displays.push(DEFAULT_DISPLAY);
+    }
+
+    let placements = simulate(layout, &displays, count, gap, scale);
+    match format {
+        Format::Table => print_table(&placements),
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&placements).map_err(|e| e.to_string())?;
+            println!("{}", json);
+        }
+        Format::Ascii => print_diagram(&displays, &placements),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_parse_with_and_without_an_origin() {
+        assert_eq!(
+            parse_display("1440x900"),
+            Ok(Rect::new(0.0, 0.0, 1440.0, 900.0))
+        );
+        assert_eq!(
+            parse_display("1920x1080+1440+0"),
+            Ok(Rect::new(1440.0, 0.0, 1920.0, 1080.0))
+        );
+        assert_eq!(
+            parse_display("1920x1080-1920-120"),
+            Ok(Rect::new(-1920.0, -120.0, 1920.0, 1080.0))
+        );
+        assert_eq!(
+            parse_display("1512.5x982+0-0.5"),
+            Ok(Rect::new(0.0, -0.5, 1512.5, 982.0))
+        );
+    }
+
+    #[test]
+    fn malformed_displays_are_rejected() {
+        for spec in [
+            "",
+            "1440",
+            "1440x",
+            "x900",
+            "1440X900",
+            "0x900",
+            "1440x-900",
+            "NaNx900",
+            "infx900",
+            "1440x900+10",
+            "1440x900+",
+            "1440x900+a+0",
+            "1440x900+0+0+0",
+        ] {
+            let err = parse_display(spec).expect_err(spec);
+            assert!(err.contains("expected WxH[+X+Y]"), "{}: {}", spec, err);
+        }
+    }
+
+    #[test]
+    fn gaps_must_be_finite_and_not_negative() {
+        assert_eq!(parse_gap("0"), Ok(0.0));
+        assert_eq!(parse_gap("12.5"), Ok(12.5));
+        for value in ["-100", "inf", "nan", "NaN", "", "ten"] {
+            assert_eq!(parse_gap(value), Err(format!("Invalid gap: {}", value)));
+        }
+    }
+
+    #[test]
+    fn scales_must_be_finite_and_positive() {
+        assert_eq!(parse_scale("2"), Ok(2.0));
+        assert_eq!(parse_scale("1.5"), Ok(1.5));
+        for value in ["0", "-1", "inf", "nan", ""] {
+            assert_eq!(parse_scale(value), Err(format!("Invalid scale: {}", value)));
+        }
+    }
+
+    #[test]
+    fn every_window_is_placed_on_every_display() {
+        let displays = [
+            Rect::new(0.0, 0.0, 1440.0, 900.0),
+            Rect::new(-1920.0, 0.0, 1920.0, 1080.0),
+        ];
+        let placements = simulate(Layout::Vertical, &displays, 3, 10.0, 2.0);
+        let placed: Vec<(usize, usize)> =
+            placements.iter().map(|p| (p.display, p.window)).collect();
+        assert_eq!(placed, [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2)]);
+        for p in &placements {
+            let display = displays[p.display];
+            assert!(display.contains(p.rect.x, p.rect.y), "{:?}", p);
+            assert!(p.rect.x + p.rect.width <= display.x + display.width);
+            assert!(p.rect.y + p.rect.height <= display.y + display.height);
+        }
+
+        assert!(simulate(Layout::Monocle, &displays, 0, 0.0, 1.0).is_empty());
+    }
+}
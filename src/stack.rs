@@ -0,0 +1,164 @@
+use crate::config::Config;
+use crate::core_graphics::main_screen_scale;
+use crate::focus::focus;
+use crate::geometry::{Direction, neighbour};
+use crate::hooks::Hooks;
+use crate::layout::gapped_layout_rects;
+use crate::window::{Window, focused_window_index};
+use crate::{State, WindowSignature, retile_windows, signature, tiled_groups, tiling_area};
+
+/// `vega stack <direction>`: moves the focused window onto the stack of the
+/// neighbouring tile, turning a lone window into a stack if needed.
+pub fn join(
+    direction: Direction,
+    config: &Config,
+    state: &mut State,
+    windows: &[Window],
+    hooks: &mut Hooks,
+) -> Result<(), String> {
+    let display = tiling_area(config, state);
+    let tiles = tiled_groups(state, windows, display);
+    let rects = gapped_layout_rects(
+        state.current_layout,
+        display,
+        tiles.len(),
+        config.gap,
+        main_screen_scale(),
+    );
+
+    let focused = focused_window_index(windows).ok_or("Could not find a focused window")?;
+    let focused_sig = signature(&windows[focused]);
+    let from = tiles
+        .iter()
+        .position(|tile| tile.iter().any(|w| signature(w) == focused_sig))
+        .ok_or("The focused window is not tiled")?;
+    let to = neighbour(&rects, from, direction).ok_or("There is no tile in that direction")?;
+    let target = signature(&tiles[to][0]);
+
+    let live: Vec<_> = windows.iter().map(signature).collect();
+    join_stack(&mut state.stacks, &focused_sig, &target, &live);
+    println!("Stacking '{}' onto '{}'", focused_sig.0, target.0);
+
+    retile_windows(config, state, windows, hooks);
+    focus(config, &windows[focused]);
+    Ok(())
+}
+
+/// `vega stack-cycle`: raises the window after the focused one in its stack.
+pub fn cycle(config: &Config, state: &mut State, windows: &[Window]) -> Result<(), String> {
+    let focused = focused_window_index(windows).ok_or("Could not find a focused window")?;
+    let focused_sig = signature(&windows[focused]);
+    let live: Vec<_> = windows.iter().map(signature).collect();
+
+    let next = raise_next(&mut state.stacks, &focused_sig, &live)
+        .ok_or("The focused window is not in a stack")?;
+
+    if let Some(window) = windows.iter().find(|w| signature(w) == next) {
+        println!("Raising '{}'", window.app_name);
+        focus(config, window);
+    }
+    Ok(())
+}
+
+/// Rotates the stack holding `focused` so the live member after it is on
+/// top, and returns that member. `None` when `focused` shares no stack with
+/// another live window.
+fn raise_next(
+    stacks: &mut [Vec<WindowSignature>],
+    focused: &WindowSignature,
+    live: &[WindowSignature],
+) -> Option<WindowSignature> {
+    let stack = stacks.iter_mut().find(|stack| stack.contains(focused))?;
+    let members: Vec<_> = stack.iter().filter(|sig| live.contains(sig)).collect();
+    if members.len() < 2 {
+        return None;
+    }
+
+    let pos = members.iter().position(|sig| *sig == focused).unwrap_or(0);
+    let next = members[(pos + 1) % members.len()].clone();
+    if let Some(index) = stack.iter().position(|sig| *sig == next) {
+        stack.rotate_left(index);
+    }
+    Some(next)
+}
+
+/// Puts `window` on top of `target`'s stack. Members that are no longer live
+/// are dropped along the way, as are stacks left with a single window.
+fn join_stack(
+    stacks: &mut Vec<Vec<WindowSignature>>,
+    window: &WindowSignature,
+    target: &WindowSignature,
+    live: &[WindowSignature],
+) {
+    for stack in stacks.iter_mut() {
+        stack.retain(|sig| sig != window && live.contains(sig));
+    }
+    stacks.retain(|stack| stack.len() > 1);
+
+    match stacks.iter_mut().find(|stack| stack.contains(target)) {
+        Some(stack) => stack.insert(0, window.clone()),
+        None => stacks.push(vec![window.clone(), target.clone()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(app: &str) -> WindowSignature {
+        (app.to_string(), String::new())
+    }
+
+    fn sigs(apps: &[&str]) -> Vec<WindowSignature> {
+        apps.iter().map(|app| sig(app)).collect()
+    }
+
+    #[test]
+    fn a_lone_window_starts_a_stack() {
+        let mut stacks = Vec::new();
+        join_stack(&mut stacks, &sig("A"), &sig("B"), &sigs(&["A", "B", "C"]));
+        assert_eq!(stacks, [sigs(&["A", "B"])]);
+
+        join_stack(&mut stacks, &sig("C"), &sig("B"), &sigs(&["A", "B", "C"]));
+        assert_eq!(stacks, [sigs(&["C", "A", "B"])]);
+    }
+
+    #[test]
+    fn a_window_moves_between_stacks() {
+        let live = sigs(&["A", "B", "C", "D", "E"]);
+        let mut stacks = vec![sigs(&["A", "B", "C"]), sigs(&["D", "E"])];
+        join_stack(&mut stacks, &sig("A"), &sig("E"), &live);
+        assert_eq!(stacks, [sigs(&["B", "C"]), sigs(&["A", "D", "E"])]);
+
+        // Leaving a stack of two breaks it up.
+        join_stack(&mut stacks, &sig("B"), &sig("D"), &live);
+        assert_eq!(stacks, [sigs(&["B", "A", "D", "E"])]);
+    }
+
+    #[test]
+    fn dead_members_are_pruned() {
+        let live = sigs(&["A", "B", "D"]);
+        let mut stacks = vec![sigs(&["Gone", "B"]), sigs(&["C", "D", "Gone"])];
+        join_stack(&mut stacks, &sig("A"), &sig("D"), &live);
+        assert_eq!(stacks, [sigs(&["A", "D"])]);
+    }
+
+    #[test]
+    fn cycling_raises_the_next_live_member() {
+        let live = sigs(&["A", "B", "C"]);
+        let mut stacks = vec![sigs(&["A", "Gone", "B", "C"])];
+        assert_eq!(raise_next(&mut stacks, &sig("A"), &live), Some(sig("B")));
+        assert_eq!(stacks, [sigs(&["B", "C", "A", "Gone"])]);
+        assert_eq!(raise_next(&mut stacks, &sig("B"), &live), Some(sig("C")));
+        assert_eq!(raise_next(&mut stacks, &sig("C"), &live), Some(sig("A")));
+        assert_eq!(stacks, [sigs(&["A", "Gone", "B", "C"])]);
+    }
+
+    #[test]
+    fn cycling_needs_a_stack_of_live_windows() {
+        let mut stacks = vec![sigs(&["A", "Gone"])];
+        assert_eq!(raise_next(&mut stacks, &sig("A"), &sigs(&["A"])), None);
+        assert_eq!(raise_next(&mut stacks, &sig("B"), &sigs(&["A", "B"])), None);
+        assert_eq!(stacks, [sigs(&["A", "Gone"])]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core_graphics::*;
 use crate::geometry::Rect;
+use crate::run_loop::{self, CFRunLoopSourceRef};
 use objc2::{
     msg_send,
     runtime::{AnyClass, AnyObject},
@@ -13,6 +14,8 @@ pub type AXUIElementRef = *const c_void;
 type CFTypeRef = *const c_void;
 type CFStringRef = *const c_void;
 type CFArrayRef = *const c_void;
+type CFDictionaryRef = *const c_void;
+type AXObserverRef = *const c_void;
 type CFIndex = isize;
 type PidT = i32;
 
@@ -29,6 +32,13 @@ impl Deref for SendableAXUIElementRef {
     }
 }
 
+pub type AXObserverCallback = extern "C" fn(
+    observer: AXObserverRef,
+    element: AXUIElementRef,
+    notification: CFStringRef,
+    refcon: *mut c_void,
+);
+
 #[repr(u32)]
 enum AXValueType {
     CGPoint = 1,
@@ -38,6 +48,15 @@ enum AXValueType {
 #[allow(improper_ctypes)]
 unsafe extern "C" {
     fn AXUIElementCreateApplication(pid: PidT) -> AXUIElementRef;
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCopyElementAtPosition(
+        application: AXUIElementRef,
+        x: f32,
+        y: f32,
+        element: *mut AXUIElementRef,
+    ) -> i32;
+    fn AXUIElementGetPid(element: AXUIElementRef, pid: *mut PidT) -> i32;
+    fn AXUIElementPerformAction(element: AXUIElementRef, action: CFStringRef) -> i32;
     fn AXUIElementCopyAttributeValue(
         element: AXUIElementRef,
         attribute: CFStringRef,
@@ -61,6 +80,37 @@ unsafe extern "C" {
     pub fn CFRetain(cf: CFTypeRef) -> CFTypeRef;
     fn CFBooleanGetValue(boolean: CFTypeRef) -> u8;
     pub fn CFEqual(cf1: CFTypeRef, cf2: CFTypeRef) -> u8;
+    fn CFDictionaryCreate(
+        alloc: CFTypeRef,
+        keys: *const CFTypeRef,
+        values: *const CFTypeRef,
+        num_values: CFIndex,
+        key_callbacks: *const c_void,
+        value_callbacks: *const c_void,
+    ) -> CFDictionaryRef;
+    fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> u8;
+    fn AXObserverCreate(
+        application: PidT,
+        callback: AXObserverCallback,
+        out_observer: *mut AXObserverRef,
+    ) -> i32;
+    fn AXObserverAddNotification(
+        observer: AXObserverRef,
+        element: AXUIElementRef,
+        notification: CFStringRef,
+        refcon: *mut c_void,
+    ) -> i32;
+    fn AXObserverRemoveNotification(
+        observer: AXObserverRef,
+        element: AXUIElementRef,
+        notification: CFStringRef,
+    ) -> i32;
+    fn AXObserverGetRunLoopSource(observer: AXObserverRef) -> CFRunLoopSourceRef;
+
+    static kCFBooleanTrue: CFTypeRef;
+    static kCFBooleanFalse: CFTypeRef;
+    static kCFTypeDictionaryKeyCallBacks: u8;
+    static kCFTypeDictionaryValueCallBacks: u8;
 }
 
 const KAX_WINDOWS: &str = "AXWindows";
@@ -68,7 +118,12 @@ const KAX_POSITION: &str = "AXPosition";
 const KAX_SIZE: &str = "AXSize";
 const KAX_MINIMIZED: &str = "AXMinimized";
 const KAX_FOCUSED_WINDOW: &str = "AXFocusedWindow";
+const KAX_WINDOW: &str = "AXWindow";
 const KAX_TITLE: &str = "AXTitle";
+const KAX_RAISE_ACTION: &str = "AXRaise";
+const KAX_WINDOW_MOVED_NOTIFICATION: &str = "AXWindowMoved";
+const KAX_WINDOW_RESIZED_NOTIFICATION: &str = "AXWindowResized";
+const KAX_TRUSTED_CHECK_OPTION_PROMPT: &str = "AXTrustedCheckOptionPrompt";
 const KCF_STRING_ENCODING_UTF8: u32 = 0x08000100;
 
 fn cfstring(s: &str) -> CFStringRef {
@@ -97,6 +152,47 @@ impl Drop for Window {
     }
 }
 
+impl Clone for Window {
+    fn clone(&self) -> Self {
+        Window {
+            ax_ref: SendableAXUIElementRef(unsafe { CFRetain(*self.ax_ref) }),
+            app_name: self.app_name.clone(),
+            title: self.title.clone(),
+        }
+    }
+}
+
+/// Whether this process has been granted Accessibility access. With `prompt`
+/// set, macOS shows its "would like to control this computer" dialog when
+/// access is missing.
+pub fn is_process_trusted(prompt: bool) -> bool {
+    unsafe {
+        let key = cfstring(KAX_TRUSTED_CHECK_OPTION_PROMPT);
+        let value = if prompt {
+            kCFBooleanTrue
+        } else {
+            kCFBooleanFalse
+        };
+        let options = CFDictionaryCreate(
+            ptr::null(),
+            &key,
+            &value,
+            1,
+            &raw const kCFTypeDictionaryKeyCallBacks as *const c_void,
+            &raw const kCFTypeDictionaryValueCallBacks as *const c_void,
+        );
+        let trusted = AXIsProcessTrustedWithOptions(options) != 0;
+        CFRelease(options);
+        CFRelease(key);
+        trusted
+    }
+}
+
+fn shared_workspace() -> *mut AnyObject {
+    let nsworkspace = AnyClass::get(c"NSWorkspace").expect("NSWorkspace class not found");
+    unsafe { msg_send![nsworkspace, sharedWorkspace] }
+}
+
 pub fn get_window_title(element: AXUIElementRef) -> String {
     unsafe {
         let mut title_ref: CFTypeRef = ptr::null();
@@ -124,9 +220,7 @@ pub fn get_window_title(element: AXUIElementRef) -> String {
 
 pub fn get_focused_window_ref() -> Option<SendableAXUIElementRef> {
     unsafe {
-        let nsworkspace_cstr = CStr::from_bytes_with_nul_unchecked(b"NSWorkspace\0");
-        let nsworkspace = AnyClass::get(nsworkspace_cstr).expect("NSWorkspace class not found");
-        let shared_workspace: *mut AnyObject = msg_send![nsworkspace, sharedWorkspace];
+        let shared_workspace = shared_workspace();
         let frontmost_app: *mut AnyObject = msg_send![shared_workspace, frontmostApplication];
 
         if frontmost_app.is_null() {
@@ -157,12 +251,78 @@ pub fn get_focused_window_ref() -> Option<SendableAXUIElementRef> {
     }
 }
 
+/// The focused window, named like the ones from [`collect_windows`].
+pub fn get_focused_window() -> Option<Window> {
+    let app_name = unsafe {
+        let shared_workspace = shared_workspace();
+        let frontmost_app: *mut AnyObject = msg_send![shared_workspace, frontmostApplication];
+        if frontmost_app.is_null() {
+            return None;
+        }
+
+        let name: *mut AnyObject = msg_send![frontmost_app, localizedName];
+        let cstr: *const c_char = msg_send![name, UTF8String];
+        if cstr.is_null() {
+            "<unknown>".to_string()
+        } else {
+            CStr::from_ptr(cstr).to_string_lossy().into_owned()
+        }
+    };
+
+    let focused_ref = get_focused_window_ref()?;
+    let title = get_window_title(*focused_ref);
+    Some(Window {
+        ax_ref: focused_ref,
+        app_name,
+        title,
+    })
+}
+
+/// Position of the focused window within `windows`.
+pub fn focused_window_index(windows: &[Window]) -> Option<usize> {
+    let focused_ref = get_focused_window_ref()?;
+    let index = windows
+        .iter()
+        .position(|w| unsafe { CFEqual(*w.ax_ref, *focused_ref) } != 0);
+    unsafe { CFRelease(*focused_ref) };
+    index
+}
+
+/// Position within `windows` of the frontmost window at the point, or
+/// `None` when another window, or none, is in front there.
+pub fn window_at(windows: &[Window], x: f64, y: f64) -> Option<usize> {
+    unsafe {
+        let system_wide = AXUIElementCreateSystemWide();
+        let mut element: AXUIElementRef = ptr::null();
+        let result =
+            AXUIElementCopyElementAtPosition(system_wide, x as f32, y as f32, &mut element);
+        CFRelease(system_wide);
+        if result != 0 || element.is_null() {
+            return None;
+        }
+
+        // The hit is usually a control inside the window; a bare window
+        // has no window of its own.
+        let mut window: CFTypeRef = ptr::null();
+        let attr = cfstring(KAX_WINDOW);
+        let result = AXUIElementCopyAttributeValue(element, attr, &mut window);
+        CFRelease(attr);
+        if result == 0 && !window.is_null() {
+            CFRelease(element);
+        } else {
+            window = element;
+        }
+
+        let index = windows.iter().position(|w| CFEqual(*w.ax_ref, window) != 0);
+        CFRelease(window);
+        index
+    }
+}
+
 pub fn collect_windows() -> Vec<Window> {
     let mut windows = Vec::new();
     unsafe {
-        let nsworkspace_cstr = CStr::from_bytes_with_nul_unchecked(b"NSWorkspace\0");
-        let nsworkspace = AnyClass::get(nsworkspace_cstr).expect("NSWorkspace class not found");
-        let shared_workspace: *mut AnyObject = msg_send![nsworkspace, sharedWorkspace];
+        let shared_workspace = shared_workspace();
         let running_apps: *mut AnyObject = msg_send![shared_workspace, runningApplications];
         let count: usize = msg_send![running_apps, count];
 
@@ -245,13 +405,17 @@ pub fn move_and_resize_window(window: &Window, rect: Rect) {
 }
 
 pub fn window_rect(window: &Window) -> Option<Rect> {
+    element_rect(*window.ax_ref)
+}
+
+pub fn element_rect(element: AXUIElementRef) -> Option<Rect> {
     unsafe {
         let mut pos_ref: CFTypeRef = ptr::null();
         let mut size_ref: CFTypeRef = ptr::null();
         let pos_attr = cfstring(KAX_POSITION);
         let size_attr = cfstring(KAX_SIZE);
-        let pos_result = AXUIElementCopyAttributeValue(*window.ax_ref, pos_attr, &mut pos_ref);
-        let size_result = AXUIElementCopyAttributeValue(*window.ax_ref, size_attr, &mut size_ref);
+        let pos_result = AXUIElementCopyAttributeValue(element, pos_attr, &mut pos_ref);
+        let size_result = AXUIElementCopyAttributeValue(element, size_attr, &mut size_ref);
         CFRelease(pos_attr);
         CFRelease(size_attr);
 
@@ -314,3 +478,98 @@ pub fn is_window_minimized(window: &Window) -> bool {
         is_minimized
     }
 }
+
+pub fn set_window_minimized(window: &Window, minimized: bool) {
+    unsafe {
+        let attr = cfstring(KAX_MINIMIZED);
+        let value = if minimized {
+            kCFBooleanTrue
+        } else {
+            kCFBooleanFalse
+        };
+        AXUIElementSetAttributeValue(*window.ax_ref, attr, value);
+        CFRelease(attr);
+    }
+}
+
+/// Raises `window` above its app's other windows.
+pub fn raise_window(window: &Window) {
+    unsafe {
+        let action = cfstring(KAX_RAISE_ACTION);
+        AXUIElementPerformAction(*window.ax_ref, action);
+        CFRelease(action);
+    }
+}
+
+/// Raises `window` and activates its app.
+pub fn focus_window(window: &Window) {
+    // NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+
+    raise_window(window);
+    unsafe {
+        let mut pid: PidT = 0;
+        if AXUIElementGetPid(*window.ax_ref, &mut pid) != 0 {
+            return;
+        }
+        let Some(running_app_class) = AnyClass::get(c"NSRunningApplication") else {
+            return;
+        };
+        let app: *mut AnyObject =
+            msg_send![running_app_class, runningApplicationWithProcessIdentifier: pid];
+        if !app.is_null() {
+            let _: bool = msg_send![app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+        }
+    }
+}
+
+/// Delivers move and resize notifications for one window to `callback` on
+/// the main run loop, until dropped.
+pub struct WindowObserver {
+    observer: AXObserverRef,
+    window: Window,
+}
+
+impl WindowObserver {
+    /// `refcon` is handed to `callback` untouched and must stay valid for as
+    /// long as the observer lives.
+    pub fn new(window: Window, callback: AXObserverCallback, refcon: *mut c_void) -> Option<Self> {
+        unsafe {
+            let mut pid: PidT = 0;
+            if AXUIElementGetPid(*window.ax_ref, &mut pid) != 0 {
+                return None;
+            }
+            let mut observer: AXObserverRef = ptr::null();
+            if AXObserverCreate(pid, callback, &mut observer) != 0 || observer.is_null() {
+                return None;
+            }
+            for name in [
+                KAX_WINDOW_MOVED_NOTIFICATION,
+                KAX_WINDOW_RESIZED_NOTIFICATION,
+            ] {
+                let notification = cfstring(name);
+                AXObserverAddNotification(observer, *window.ax_ref, notification, refcon);
+                CFRelease(notification);
+            }
+            run_loop::add_source(AXObserverGetRunLoopSource(observer));
+            Some(WindowObserver { observer, window })
+        }
+    }
+}
+
+impl Drop for WindowObserver {
+    fn drop(&mut self) {
+        unsafe {
+            for name in [
+                KAX_WINDOW_MOVED_NOTIFICATION,
+                KAX_WINDOW_RESIZED_NOTIFICATION,
+            ] {
+                let notification = cfstring(name);
+                AXObserverRemoveNotification(self.observer, *self.window.ax_ref, notification);
+                CFRelease(notification);
+            }
+            run_loop::remove_source(AXObserverGetRunLoopSource(self.observer));
+            CFRelease(self.observer);
+        }
+    }
+}
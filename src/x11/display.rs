@@ -0,0 +1,177 @@
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_void};
+use std::slice;
+
+use x11::{xlib, xrandr};
+
+use crate::geometry::{Insets, Rect};
+use crate::window::{connection, work_area};
+
+/// The atom naming a monitor.
+pub type DisplayId = xlib::Atom;
+
+/// An XRandR monitor and the ways config and the command line can name it.
+#[derive(Debug, Clone)]
+pub struct Display {
+    pub id: DisplayId,
+    /// Position in [`displays`].
+    pub index: usize,
+    /// X11 has no display UUIDs; the output name is the closest stable key.
+    pub uuid: String,
+    /// The output name, e.g. "DP-1".
+    pub name: String,
+    /// Always 1: X11 places windows in device pixels.
+    pub scale: f64,
+    /// The primary monitor, where tiling happens.
+    pub main: bool,
+    pub bounds: Rect,
+}
+
+impl Display {
+    /// A display at 1x, the main one when `index` is 0.
+    #[cfg(test)]
+    pub fn new(index: usize, uuid: &str, name: &str, bounds: Rect) -> Display {
+        Display {
+            id: Default::default(),
+            index,
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            scale: 1.0,
+            main: index == 0,
+            bounds,
+        }
+    }
+
+    /// Whether `selector` is this display's index, name or UUID.
+    pub fn matches(&self, selector: &str) -> bool {
+        selector == self.index.to_string()
+            || (!self.name.is_empty() && selector == self.name)
+            || (!self.uuid.is_empty() && selector.eq_ignore_ascii_case(&self.uuid))
+    }
+}
+
+/// Monitors as XRandR reports them, primary first and the rest from left to
+/// right. Falls back to the whole screen when RandR knows of none.
+pub fn displays() -> Vec<Display> {
+    let Some(conn) = connection() else {
+        return Vec::new();
+    };
+    let mut displays = Vec::new();
+
+    unsafe {
+        let mut count: c_int = 0;
+        let monitors = xrandr::XRRGetMonitors(conn.display, conn.root, xlib::True, &mut count);
+        if !monitors.is_null() {
+            for monitor in slice::from_raw_parts(monitors, count as usize) {
+                let mut name = String::new();
+                let atom_name = xlib::XGetAtomName(conn.display, monitor.name);
+                if !atom_name.is_null() {
+                    name = CStr::from_ptr(atom_name).to_string_lossy().into_owned();
+                    xlib::XFree(atom_name as *mut c_void);
+                }
+                displays.push(Display {
+                    id: monitor.name,
+                    index: 0,
+                    uuid: name.clone(),
+                    name,
+                    scale: 1.0,
+                    main: monitor.primary != xlib::False,
+                    bounds: Rect {
+                        x: monitor.x as f64,
+                        y: monitor.y as f64,
+                        width: monitor.width as f64,
+                        height: monitor.height as f64,
+                    },
+                });
+            }
+            xrandr::XRRFreeMonitors(monitors);
+        }
+
+        if displays.is_empty() {
+            let screen = xlib::XDefaultScreen(conn.display);
+            displays.push(Display {
+                id: 0,
+                index: 0,
+                uuid: String::new(),
+                name: String::new(),
+                scale: 1.0,
+                main: true,
+                bounds: Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: xlib::XDisplayWidth(conn.display, screen) as f64,
+                    height: xlib::XDisplayHeight(conn.display, screen) as f64,
+                },
+            });
+        }
+    }
+
+    displays.sort_by(|a, b| {
+        b.main
+            .cmp(&a.main)
+            .then(a.bounds.x.total_cmp(&b.bounds.x))
+            .then(a.bounds.y.total_cmp(&b.bounds.y))
+    });
+    // Without a primary monitor the leftmost one stands in.
+    if let Some(first) = displays.first_mut() {
+        first.main = true;
+    }
+    for (index, display) in displays.iter_mut().enumerate() {
+        display.index = index;
+    }
+    displays
+}
+
+pub fn main_screen_rect() -> Rect {
+    displays().first().map(|d| d.bounds).unwrap_or(Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 0.0,
+        height: 0.0,
+    })
+}
+
+/// Space panels and docks reserve along each edge of the main monitor,
+/// going by the window manager's work area for the current desktop.
+pub fn main_screen_insets() -> Insets {
+    let (Some(area), main) = (work_area(), main_screen_rect()) else {
+        return Insets::default();
+    };
+    Insets {
+        top: (area.y - main.y).max(0.0),
+        bottom: ((main.y + main.height) - (area.y + area.height)).max(0.0),
+        left: (area.x - main.x).max(0.0),
+        right: ((main.x + main.width) - (area.x + area.width)).max(0.0),
+    }
+}
+
+/// X11 places windows in device pixels, so there is no finer grid to align
+/// to.
+pub fn main_screen_scale() -> f64 {
+    1.0
+}
+
+/// Display changes would come as RandR events, which nothing reads yet, so
+/// `callback` is never called and hotplug is only followed on macOS.
+pub fn on_display_reconfigured(_callback: impl FnMut(bool) + 'static) {}
+
+/// Moves the pointer to root coordinates `x`, `y`.
+pub fn warp_mouse(x: f64, y: f64) {
+    let Some(conn) = connection() else {
+        return;
+    };
+    unsafe {
+        xlib::XWarpPointer(
+            conn.display,
+            0,
+            conn.root,
+            0,
+            0,
+            0,
+            0,
+            x as c_int,
+            y as c_int,
+        );
+        xlib::XFlush(conn.display);
+    }
+}
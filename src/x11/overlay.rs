@@ -0,0 +1,23 @@
+use crate::config::Config;
+use crate::run_loop;
+use crate::window::Window;
+
+/// Nothing to set up: X11 has no application object to configure.
+pub fn init_app() {}
+
+/// Runs the timer loop in place of an application event loop. Never
+/// returns.
+pub fn run_app() {
+    run_loop::run();
+}
+
+/// The focus border is only drawn on macOS for now.
+pub struct FocusBorder;
+
+impl FocusBorder {
+    pub fn new(_config: &Config) -> Result<Self, String> {
+        Err("The focus border is not supported on X11 yet; disable border.enabled".to_string())
+    }
+
+    pub fn follow(&mut self, _window: Option<Window>) {}
+}
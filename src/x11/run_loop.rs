@@ -0,0 +1,46 @@
+use std::cell::RefCell;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Timer {
+    interval: Duration,
+    next: Instant,
+    callback: Box<dyn FnMut()>,
+}
+
+thread_local! {
+    static TIMERS: RefCell<Vec<Timer>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `callback` on this thread every `interval` for as long as `run`
+/// runs.
+pub fn every(interval: Duration, callback: impl FnMut() + 'static) {
+    TIMERS.with_borrow_mut(|timers| {
+        timers.push(Timer {
+            interval,
+            next: Instant::now() + interval,
+            callback: Box::new(callback),
+        })
+    });
+}
+
+/// Sleeps until the next timer is due and fires it, over and over. Only
+/// returns if no timers are left.
+pub fn run() {
+    loop {
+        let Some(next) = TIMERS.with_borrow(|timers| timers.iter().map(|t| t.next).min()) else {
+            return;
+        };
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+
+        // Taken out so callbacks can add timers of their own.
+        let mut timers = TIMERS.take();
+        let now = Instant::now();
+        for timer in timers.iter_mut().filter(|t| t.next <= now) {
+            (timer.callback)();
+            timer.next = now + timer.interval;
+        }
+        TIMERS.with_borrow_mut(|added| timers.append(added));
+        TIMERS.set(timers);
+    }
+}
@@ -0,0 +1,602 @@
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_long, c_uchar, c_uint, c_ulong, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::OnceLock;
+
+use x11::xlib;
+
+use crate::geometry::Rect;
+
+// Source indication for EWMH requests: a pager or similar tool, which
+// window managers should not second-guess the way they might an app.
+const SOURCE_PAGER: c_long = 2;
+// _NET_WM_STATE actions
+const STATE_REMOVE: c_long = 0;
+// _NET_MOVERESIZE_WINDOW: NorthWest gravity with x, y, width and height set.
+const MOVERESIZE_FLAGS: c_long = 1 | 0xf << 8 | SOURCE_PAGER << 12;
+// RevertToParent
+const REVERT_TO_PARENT: c_int = 2;
+
+/// The display connection shared by every call, opened on first use.
+pub struct Connection {
+    pub display: *mut xlib::Display,
+    pub root: xlib::Window,
+}
+
+// Xlib locks the display itself once `XInitThreads` has run.
+unsafe impl Send for Connection {}
+unsafe impl Sync for Connection {}
+
+static CONNECTION: OnceLock<Option<Connection>> = OnceLock::new();
+
+// Windows can vanish between listing and querying them; the default handler
+// would exit the process over the resulting BadWindow.
+unsafe extern "C" fn ignore_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    0
+}
+
+/// The connection to `$DISPLAY`, or `None` when no X server can be reached.
+pub fn connection() -> Option<&'static Connection> {
+    CONNECTION
+        .get_or_init(|| unsafe {
+            xlib::XInitThreads();
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return None;
+            }
+            xlib::XSetErrorHandler(Some(ignore_error));
+            Some(Connection {
+                display,
+                root: xlib::XDefaultRootWindow(display),
+            })
+        })
+        .as_ref()
+}
+
+fn atom(conn: &Connection, name: &CStr) -> xlib::Atom {
+    unsafe { xlib::XInternAtom(conn.display, name.as_ptr(), xlib::False) }
+}
+
+/// Reads a property of any type and its raw items; format 32 items come
+/// back from Xlib as longs.
+fn property(conn: &Connection, window: xlib::Window, name: &CStr) -> Option<(c_int, Vec<u8>)> {
+    unsafe {
+        let mut actual_type = 0;
+        let mut format = 0;
+        let mut count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+        let status = xlib::XGetWindowProperty(
+            conn.display,
+            window,
+            atom(conn, name),
+            0,
+            c_long::MAX / 4,
+            xlib::False,
+            xlib::AnyPropertyType as c_ulong,
+            &mut actual_type,
+            &mut format,
+            &mut count,
+            &mut bytes_after,
+            &mut data,
+        );
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+        let item_size = match format {
+            8 => 1,
+            16 => size_of::<i16>(),
+            32 => size_of::<c_long>(),
+            _ => 0,
+        };
+        let bytes = slice::from_raw_parts(data, count as usize * item_size).to_vec();
+        xlib::XFree(data as *mut c_void);
+        Some((format, bytes))
+    }
+}
+
+fn property_longs(conn: &Connection, window: xlib::Window, name: &CStr) -> Vec<c_ulong> {
+    match property(conn, window, name) {
+        Some((32, bytes)) => bytes
+            .chunks_exact(size_of::<c_ulong>())
+            .map(|chunk| c_ulong::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn property_string(conn: &Connection, window: xlib::Window, name: &CStr) -> Option<String> {
+    match property(conn, window, name) {
+        Some((8, bytes)) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        _ => None,
+    }
+}
+
+fn has_state(conn: &Connection, window: xlib::Window, state: &CStr) -> bool {
+    property_longs(conn, window, c"_NET_WM_STATE").contains(&atom(conn, state))
+}
+
+/// Sends an EWMH request about `window` to the window manager.
+fn send_client_message(
+    conn: &Connection,
+    window: xlib::Window,
+    message_type: &CStr,
+    data: [c_long; 5],
+) {
+    let event = xlib::XClientMessageEvent {
+        type_: xlib::ClientMessage,
+        serial: 0,
+        send_event: xlib::True,
+        display: conn.display,
+        window,
+        message_type: atom(conn, message_type),
+        format: 32,
+        data: xlib::ClientMessageData::from(data),
+    };
+    let mut event = xlib::XEvent::from(event);
+    unsafe {
+        xlib::XSendEvent(
+            conn.display,
+            conn.root,
+            xlib::False,
+            xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+            &mut event,
+        );
+        xlib::XFlush(conn.display);
+    }
+}
+
+/// The name the EWMH window manager gives itself, if one is running.
+pub fn window_manager_name() -> Option<String> {
+    let conn = connection()?;
+    let check = *property_longs(conn, conn.root, c"_NET_SUPPORTING_WM_CHECK").first()?;
+    Some(property_string(conn, check, c"_NET_WM_NAME").unwrap_or_default())
+}
+
+/// `_NET_WORKAREA` for the current desktop: the screen minus panels and
+/// docks, as one rect spanning every monitor.
+pub fn work_area() -> Option<Rect> {
+    let conn = connection()?;
+    let desktop = property_longs(conn, conn.root, c"_NET_CURRENT_DESKTOP")
+        .first()
+        .copied()
+        .unwrap_or(0) as usize;
+    let areas = property_longs(conn, conn.root, c"_NET_WORKAREA");
+    match areas.get(desktop * 4..desktop * 4 + 4)? {
+        &[x, y, width, height] => Some(Rect {
+            x: x as f64,
+            y: y as f64,
+            width: width as f64,
+            height: height as f64,
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Clone)]
+pub struct Window {
+    pub id: xlib::Window,
+    pub app_name: String,
+    pub title: String,
+}
+
+impl PartialEq for Window {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl Eq for Window {}
+
+/// X11 has no per-process access control, so this always holds; whether a
+/// display can be reached is up to `connection`.
+pub fn is_process_trusted(_prompt: bool) -> bool {
+    true
+}
+
+fn app_name(conn: &Connection, window: xlib::Window) -> Option<String> {
+    unsafe {
+        let mut hint = xlib::XClassHint {
+            res_name: ptr::null_mut(),
+            res_class: ptr::null_mut(),
+        };
+        if xlib::XGetClassHint(conn.display, window, &mut hint) == 0 {
+            return None;
+        }
+        let class = (!hint.res_class.is_null()).then(|| {
+            CStr::from_ptr(hint.res_class)
+                .to_string_lossy()
+                .into_owned()
+        });
+        for field in [hint.res_name, hint.res_class] {
+            if !field.is_null() {
+                xlib::XFree(field as *mut c_void);
+            }
+        }
+        class
+    }
+}
+
+fn window_title(conn: &Connection, window: xlib::Window) -> String {
+    property_string(conn, window, c"_NET_WM_NAME")
+        .or_else(|| property_string(conn, window, c"WM_NAME"))
+        .unwrap_or_else(|| "<Untitled>".to_string())
+}
+
+fn describe(conn: &Connection, id: xlib::Window) -> Window {
+    Window {
+        id,
+        app_name: app_name(conn, id).unwrap_or_else(|| "<unknown>".to_string()),
+        title: window_title(conn, id),
+    }
+}
+
+/// Whether `window` is an ordinary application window rather than a dock,
+/// menu, splash screen and so on. Windows without a type count as normal.
+fn is_normal(conn: &Connection, window: xlib::Window) -> bool {
+    let types = property_longs(conn, window, c"_NET_WM_WINDOW_TYPE");
+    types.is_empty() || types.contains(&atom(conn, c"_NET_WM_WINDOW_TYPE_NORMAL"))
+}
+
+fn is_viewable(conn: &Connection, window: xlib::Window) -> bool {
+    unsafe {
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, window, &mut attributes) != 0
+            && attributes.map_state == xlib::IsViewable
+    }
+}
+
+/// Top-level windows straight from the X server, for when no window
+/// manager publishes `_NET_CLIENT_LIST`.
+fn top_level_windows(conn: &Connection) -> Vec<xlib::Window> {
+    unsafe {
+        let mut root = 0;
+        let mut parent = 0;
+        let mut children: *mut xlib::Window = ptr::null_mut();
+        let mut count: c_uint = 0;
+        if xlib::XQueryTree(
+            conn.display,
+            conn.root,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut count,
+        ) == 0
+            || children.is_null()
+        {
+            return Vec::new();
+        }
+        let windows = slice::from_raw_parts(children, count as usize).to_vec();
+        xlib::XFree(children as *mut c_void);
+        windows
+            .into_iter()
+            .filter(|&w| app_name(conn, w).is_some())
+            .collect()
+    }
+}
+
+/// The focused window, named like the ones from [`collect_windows`].
+pub fn get_focused_window() -> Option<Window> {
+    let conn = connection()?;
+    let id = match window_manager_name() {
+        Some(_) => *property_longs(conn, conn.root, c"_NET_ACTIVE_WINDOW").first()?,
+        None => unsafe {
+            let mut focus = 0;
+            let mut revert_to = 0;
+            xlib::XGetInputFocus(conn.display, &mut focus, &mut revert_to);
+            focus
+        },
+    };
+    // None and PointerRoot
+    if id <= 1 {
+        return None;
+    }
+    Some(describe(conn, id))
+}
+
+/// Position of the focused window within `windows`.
+pub fn focused_window_index(windows: &[Window]) -> Option<usize> {
+    let focused = get_focused_window()?;
+    windows.iter().position(|w| *w == focused)
+}
+
+pub fn collect_windows() -> Vec<Window> {
+    let Some(conn) = connection() else {
+        return Vec::new();
+    };
+    let ids = match window_manager_name() {
+        Some(_) => property_longs(conn, conn.root, c"_NET_CLIENT_LIST"),
+        None => top_level_windows(conn),
+    };
+    ids.into_iter()
+        .filter(|&id| is_normal(conn, id))
+        .map(|id| describe(conn, id))
+        .filter(|w| !w.title.is_empty())
+        .collect()
+}
+
+/// Top-level windows from the frontmost down.
+fn stacking_order(conn: &Connection) -> Vec<xlib::Window> {
+    let mut ids = match window_manager_name() {
+        Some(_) => property_longs(conn, conn.root, c"_NET_CLIENT_LIST_STACKING"),
+        // The server lists children bottom to top.
+        None => top_level_windows(conn),
+    };
+    ids.reverse();
+    ids
+}
+
+/// Position within `windows` of the frontmost window at the point, or
+/// `None` when another window, or none, is in front there.
+pub fn window_at(windows: &[Window], x: f64, y: f64) -> Option<usize> {
+    let conn = connection()?;
+    let front = stacking_order(conn).into_iter().find(|&id| {
+        let window = Window {
+            id,
+            app_name: String::new(),
+            title: String::new(),
+        };
+        !is_window_minimized(&window) && window_rect(&window).is_some_and(|r| r.contains(x, y))
+    })?;
+    windows.iter().position(|w| w.id == front)
+}
+
+/// Space the window manager's decorations take around the client window:
+/// left, right, top and bottom.
+fn frame_extents(conn: &Connection, window: xlib::Window) -> [f64; 4] {
+    match property_longs(conn, window, c"_NET_FRAME_EXTENTS")[..] {
+        [left, right, top, bottom] => [left as f64, right as f64, top as f64, bottom as f64],
+        _ => [0.0; 4],
+    }
+}
+
+/// Moves and resizes the window frame, decorations included, to `rect`.
+pub fn move_and_resize_window(window: &Window, rect: Rect) {
+    let Some(conn) = connection() else {
+        return;
+    };
+    let [left, right, top, bottom] = frame_extents(conn, window.id);
+    let width = (rect.width - left - right).max(1.0);
+    let height = (rect.height - top - bottom).max(1.0);
+
+    if window_manager_name().is_none() {
+        unsafe {
+            xlib::XMoveResizeWindow(
+                conn.display,
+                window.id,
+                rect.x as c_int,
+                rect.y as c_int,
+                width as c_uint,
+                height as c_uint,
+            );
+            xlib::XFlush(conn.display);
+        }
+        return;
+    }
+
+    // Most window managers ignore geometry requests for maximized windows.
+    send_client_message(
+        conn,
+        window.id,
+        c"_NET_WM_STATE",
+        [
+            STATE_REMOVE,
+            atom(conn, c"_NET_WM_STATE_MAXIMIZED_VERT") as c_long,
+            atom(conn, c"_NET_WM_STATE_MAXIMIZED_HORZ") as c_long,
+            SOURCE_PAGER,
+            0,
+        ],
+    );
+    send_client_message(
+        conn,
+        window.id,
+        c"_NET_MOVERESIZE_WINDOW",
+        [
+            MOVERESIZE_FLAGS,
+            rect.x as c_long,
+            rect.y as c_long,
+            width as c_long,
+            height as c_long,
+        ],
+    );
+}
+
+/// The window frame in root coordinates, decorations included.
+pub fn window_rect(window: &Window) -> Option<Rect> {
+    let conn = connection()?;
+    unsafe {
+        let mut root = 0;
+        let (mut x, mut y) = (0, 0);
+        let (mut width, mut height, mut border, mut depth) = (0, 0, 0, 0);
+        if xlib::XGetGeometry(
+            conn.display,
+            window.id,
+            &mut root,
+            &mut x,
+            &mut y,
+            &mut width,
+            &mut height,
+            &mut border,
+            &mut depth,
+        ) == 0
+        {
+            return None;
+        }
+        let mut child = 0;
+        if xlib::XTranslateCoordinates(
+            conn.display,
+            window.id,
+            conn.root,
+            0,
+            0,
+            &mut x,
+            &mut y,
+            &mut child,
+        ) == 0
+        {
+            return None;
+        }
+        let [left, right, top, bottom] = frame_extents(conn, window.id);
+        Some(Rect {
+            x: x as f64 - left,
+            y: y as f64 - top,
+            width: width as f64 + left + right,
+            height: height as f64 + top + bottom,
+        })
+    }
+}
+
+pub fn is_window_minimized(window: &Window) -> bool {
+    let Some(conn) = connection() else {
+        return false;
+    };
+    match window_manager_name() {
+        Some(_) => has_state(conn, window.id, c"_NET_WM_STATE_HIDDEN"),
+        None => !is_viewable(conn, window.id),
+    }
+}
+
+pub fn set_window_minimized(window: &Window, minimized: bool) {
+    let Some(conn) = connection() else {
+        return;
+    };
+    unsafe {
+        if !minimized {
+            // Mapping an iconic window asks the window manager to restore it.
+            xlib::XMapWindow(conn.display, window.id);
+        } else if window_manager_name().is_some() {
+            let screen = xlib::XDefaultScreen(conn.display);
+            xlib::XIconifyWindow(conn.display, window.id, screen);
+        } else {
+            xlib::XUnmapWindow(conn.display, window.id);
+        }
+        xlib::XFlush(conn.display);
+    }
+}
+
+/// Raises `window` above the other windows.
+pub fn raise_window(window: &Window) {
+    let Some(conn) = connection() else {
+        return;
+    };
+    if window_manager_name().is_some() {
+        // No sibling, and a detail of Above.
+        send_client_message(
+            conn,
+            window.id,
+            c"_NET_RESTACK_WINDOW",
+            [SOURCE_PAGER, 0, 0, 0, 0],
+        );
+    } else {
+        unsafe {
+            xlib::XRaiseWindow(conn.display, window.id);
+            xlib::XFlush(conn.display);
+        }
+    }
+}
+
+/// Raises `window` and gives it the input focus.
+pub fn focus_window(window: &Window) {
+    let Some(conn) = connection() else {
+        return;
+    };
+    if window_manager_name().is_some() {
+        send_client_message(
+            conn,
+            window.id,
+            c"_NET_ACTIVE_WINDOW",
+            [SOURCE_PAGER, xlib::CurrentTime as c_long, 0, 0, 0],
+        );
+    } else {
+        unsafe {
+            xlib::XRaiseWindow(conn.display, window.id);
+            xlib::XSetInputFocus(conn.display, window.id, REVERT_TO_PARENT, xlib::CurrentTime);
+            xlib::XFlush(conn.display);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::raw::c_char;
+
+    // Run with `xvfb-run cargo test --features x11 -- --ignored`.
+    fn create_window(class: &CStr, title: &CStr) -> Window {
+        let conn = connection().expect("no X display");
+        unsafe {
+            let id = xlib::XCreateSimpleWindow(conn.display, conn.root, 0, 0, 200, 100, 0, 0, 0);
+            let mut hint = xlib::XClassHint {
+                res_name: class.as_ptr() as *mut c_char,
+                res_class: class.as_ptr() as *mut c_char,
+            };
+            xlib::XSetClassHint(conn.display, id, &mut hint);
+            xlib::XStoreName(conn.display, id, title.as_ptr());
+            xlib::XMapWindow(conn.display, id);
+            xlib::XSync(conn.display, xlib::False);
+            describe(conn, id)
+        }
+    }
+
+    fn sync() {
+        let conn = connection().unwrap();
+        unsafe { xlib::XSync(conn.display, xlib::False) };
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn collects_named_windows() {
+        let window = create_window(c"VegaCollect", c"collect me");
+        assert_eq!(window.app_name, "VegaCollect");
+        assert_eq!(window.title, "collect me");
+        assert!(collect_windows().contains(&window));
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn moves_and_resizes_windows() {
+        let window = create_window(c"VegaMove", c"move me");
+        let rect = Rect {
+            x: 40.0,
+            y: 30.0,
+            width: 320.0,
+            height: 240.0,
+        };
+        move_and_resize_window(&window, rect);
+        sync();
+        assert_eq!(window_rect(&window), Some(rect));
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn finds_the_frontmost_window_at_a_point() {
+        let below = create_window(c"VegaBelow", c"below");
+        let above = create_window(c"VegaAbove", c"above");
+        let windows = [below.clone(), above.clone()];
+        raise_window(&above);
+        sync();
+        assert_eq!(window_at(&windows, 10.0, 10.0), Some(1));
+        assert_eq!(window_at(&windows[..1], 10.0, 10.0), None);
+
+        raise_window(&below);
+        sync();
+        assert_eq!(window_at(&windows, 10.0, 10.0), Some(0));
+    }
+
+    #[test]
+    #[ignore = "needs an X server"]
+    fn focuses_and_minimizes_windows() {
+        let window = create_window(c"VegaFocus", c"focus me");
+        focus_window(&window);
+        sync();
+        assert_eq!(get_focused_window().map(|w| w.id), Some(window.id));
+        assert!(!is_window_minimized(&window));
+
+        set_window_minimized(&window, true);
+        sync();
+        assert!(is_window_minimized(&window));
+        set_window_minimized(&window, false);
+        sync();
+        assert!(!is_window_minimized(&window));
+    }
+}